@@ -0,0 +1,518 @@
+use crate::expr::{Expr, System};
+use crate::truth_table::system_variables;
+use std::collections::HashMap;
+
+// Node ids of the two terminals
+pub const FALSE: usize = 0;
+pub const TRUE: usize = 1;
+
+// A decision on variable `var`: `low` when it is 0, `high` when it is 1.
+// Terminals use `var` = the number of variables, so they sort below every level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Node {
+    pub var: usize,
+    pub low: usize,
+    pub high: usize,
+}
+
+// A reduced ordered BDD manager. Variables are tested in the order of `vars`,
+// and every function built in it shares the same nodes, so two functions are
+// equal exactly when their ids are
+pub struct Bdd {
+    pub vars: Vec<String>,
+    pub nodes: Vec<Node>,
+    unique: HashMap<Node, usize>,
+    computed: HashMap<(usize, usize, usize), usize>,
+}
+
+impl Bdd {
+    pub fn new(vars: Vec<String>) -> Bdd {
+        let n = vars.len();
+        let terminal = Node { var: n, low: FALSE, high: FALSE };
+        Bdd {
+            vars,
+            nodes: vec![terminal, Node { high: TRUE, low: TRUE, ..terminal }],
+            unique: HashMap::new(),
+            computed: HashMap::new(),
+        }
+    }
+
+    // The node for `var ? high : low`, reusing an existing one if there is one
+    fn make(&mut self, var: usize, low: usize, high: usize) -> usize {
+        if low == high {
+            return low;
+        }
+        let node = Node { var, low, high };
+        if let Some(&id) = self.unique.get(&node) {
+            return id;
+        }
+        self.nodes.push(node);
+        self.unique.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    pub fn var(&mut self, var: usize) -> usize {
+        self.make(var, FALSE, TRUE)
+    }
+
+    // Both branches of `f` on `var`, or `f` twice if it does not test `var` here
+    fn branches(&self, f: usize, var: usize) -> (usize, usize) {
+        let node = self.nodes[f];
+        if node.var == var { (node.low, node.high) } else { (f, f) }
+    }
+
+    // if f then g else h: every other operation is built on this
+    pub fn ite(&mut self, f: usize, g: usize, h: usize) -> usize {
+        if f == TRUE || g == h {
+            return g;
+        }
+        if f == FALSE {
+            return h;
+        }
+        if g == TRUE && h == FALSE {
+            return f;
+        }
+        if let Some(&result) = self.computed.get(&(f, g, h)) {
+            return result;
+        }
+
+        let top = self.nodes[f].var.min(self.nodes[g].var).min(self.nodes[h].var);
+        let (f0, f1) = self.branches(f, top);
+        let (g0, g1) = self.branches(g, top);
+        let (h0, h1) = self.branches(h, top);
+        let low = self.ite(f0, g0, h0);
+        let high = self.ite(f1, g1, h1);
+        let result = self.make(top, low, high);
+        self.computed.insert((f, g, h), result);
+        result
+    }
+
+    pub fn not(&mut self, f: usize) -> usize {
+        self.ite(f, FALSE, TRUE)
+    }
+
+    pub fn and(&mut self, f: usize, g: usize) -> usize {
+        self.ite(f, g, FALSE)
+    }
+
+    pub fn or(&mut self, f: usize, g: usize) -> usize {
+        self.ite(f, TRUE, g)
+    }
+
+    pub fn xor(&mut self, f: usize, g: usize) -> usize {
+        let not_g = self.not(g);
+        self.ite(f, not_g, g)
+    }
+
+    // The function of an expression. `wires` gives the functions of the
+    // `let` definitions it may refer to
+    pub fn build(&mut self, expr: &Expr, wires: &HashMap<String, usize>) -> usize {
+        match expr {
+            Expr::Var(name) => {
+                let var = self.vars.iter().position(|v| v == name).expect("variable outside the BDD");
+                self.var(var)
+            }
+            Expr::Ref(name) => wires[name],
+            Expr::Const(value) => if *value { TRUE } else { FALSE },
+            Expr::Not(inner) => {
+                let f = self.build(inner, wires);
+                self.not(f)
+            }
+            Expr::And(a, b) | Expr::Or(a, b) | Expr::Xor(a, b) | Expr::Xnor(a, b)
+            | Expr::Nand(a, b) | Expr::Nor(a, b) | Expr::Implies(a, b) | Expr::Iff(a, b) => {
+                let f = self.build(a, wires);
+                let g = self.build(b, wires);
+                match expr {
+                    Expr::And(..) => self.and(f, g),
+                    Expr::Or(..) => self.or(f, g),
+                    Expr::Xor(..) => self.xor(f, g),
+                    Expr::Implies(..) => self.ite(f, g, TRUE),
+                    _ => {
+                        let positive = match expr {
+                            Expr::Xnor(..) | Expr::Iff(..) => self.xor(f, g),
+                            Expr::Nand(..) => self.and(f, g),
+                            _ => self.or(f, g),
+                        };
+                        self.not(positive)
+                    }
+                }
+            }
+        }
+    }
+
+    // Nodes reachable from any of `roots`, terminals included
+    pub fn size(&self, roots: &[usize]) -> usize {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = roots.to_vec();
+        let mut count = 0;
+        while let Some(id) = stack.pop() {
+            if std::mem::replace(&mut seen[id], true) {
+                continue;
+            }
+            count += 1;
+            if id > TRUE {
+                stack.push(self.nodes[id].low);
+                stack.push(self.nodes[id].high);
+            }
+        }
+        count
+    }
+
+    // One satisfying assignment, as the variables it has to fix. Any variable
+    // left out may take either value
+    pub fn satisfy_one(&self, f: usize) -> Option<Vec<(usize, bool)>> {
+        if f == FALSE {
+            return None;
+        }
+        let mut assignment = Vec::new();
+        let mut id = f;
+        while id != TRUE {
+            let node = self.nodes[id];
+            // Every non-terminal node has a path to TRUE, so avoid only FALSE
+            let value = node.low == FALSE;
+            assignment.push((node.var, value));
+            id = if value { node.high } else { node.low };
+        }
+        Some(assignment)
+    }
+
+    // Number of assignments of all the variables that make `f` true. Saturates
+    // at u128::MAX, which only matters past 127 variables
+    pub fn count_models(&self, f: usize) -> u128 {
+        let mut memo = HashMap::new();
+        let below = self.count_below(f, &mut memo);
+        below.saturating_mul(power_of_two(self.nodes[f].var))
+    }
+
+    // Models over the variables from the level of `f` down
+    fn count_below(&self, f: usize, memo: &mut HashMap<usize, u128>) -> u128 {
+        if f <= TRUE {
+            return f as u128;
+        }
+        if let Some(&count) = memo.get(&f) {
+            return count;
+        }
+        let node = self.nodes[f];
+        let [low, high] = [node.low, node.high].map(|child| {
+            let skipped = self.nodes[child].var - node.var - 1;
+            self.count_below(child, memo).saturating_mul(power_of_two(skipped))
+        });
+        let count = low.saturating_add(high);
+        memo.insert(f, count);
+        count
+    }
+}
+
+impl Bdd {
+    // The same functions rebuilt in a new manager that tests the variables in
+    // `order`, a permutation of this manager's variable indices, top first
+    pub fn reorder(&self, roots: &[usize], order: &[usize]) -> (Bdd, Vec<usize>) {
+        let mut level = vec![0; order.len()];
+        for (position, &var) in order.iter().enumerate() {
+            level[var] = position;
+        }
+        let mut bdd = Bdd::new(order.iter().map(|&var| self.vars[var].clone()).collect());
+        let mut memo = HashMap::from([(FALSE, FALSE), (TRUE, TRUE)]);
+        let roots = roots.iter().map(|&f| self.copy_into(&mut bdd, f, &level, &mut memo)).collect();
+        (bdd, roots)
+    }
+
+    fn copy_into(&self, bdd: &mut Bdd, f: usize, level: &[usize], memo: &mut HashMap<usize, usize>) -> usize {
+        if let Some(&id) = memo.get(&f) {
+            return id;
+        }
+        let node = self.nodes[f];
+        let low = self.copy_into(bdd, node.low, level, memo);
+        let high = self.copy_into(bdd, node.high, level, memo);
+        let var = bdd.var(level[node.var]);
+        let id = bdd.ite(var, high, low);
+        memo.insert(f, id);
+        id
+    }
+
+    // Nodes testing each variable, reachable from `roots`
+    pub fn level_sizes(&self, roots: &[usize]) -> Vec<usize> {
+        let mut sizes = vec![0; self.vars.len()];
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = roots.to_vec();
+        while let Some(id) = stack.pop() {
+            if id <= TRUE || std::mem::replace(&mut seen[id], true) {
+                continue;
+            }
+            let node = self.nodes[id];
+            sizes[node.var] += 1;
+            stack.push(node.low);
+            stack.push(node.high);
+        }
+        sizes
+    }
+
+    // Rudell's sifting, starting from `order`: each variable in turn, busiest
+    // level first, is tried at every position and left where the shared BDD
+    // is smallest. Each try rebuilds the BDD rather than swapping levels in
+    // place, which is plenty fast at the sizes that can be drawn
+    pub fn sift(&self, roots: &[usize], order: &[usize]) -> Vec<usize> {
+        let size_with = |order: &[usize]| {
+            let (bdd, roots) = self.reorder(roots, order);
+            bdd.size(&roots)
+        };
+
+        let (start, start_roots) = self.reorder(roots, order);
+        let sizes = start.level_sizes(&start_roots);
+        let mut by_size: Vec<usize> = (0..order.len()).collect();
+        by_size.sort_by_key(|&level| std::cmp::Reverse(sizes[level]));
+        let vars: Vec<usize> = by_size.into_iter().map(|level| order[level]).collect();
+
+        let mut order = order.to_vec();
+        let mut best = size_with(&order);
+        for var in vars {
+            let from = order.iter().position(|&v| v == var).unwrap();
+            order.remove(from);
+            let mut best_position = from;
+            for position in 0..=order.len() {
+                if position == from {
+                    continue;
+                }
+                order.insert(position, var);
+                let size = size_with(&order);
+                if size < best {
+                    best = size;
+                    best_position = position;
+                }
+                order.remove(position);
+            }
+            order.insert(best_position, var);
+        }
+        order
+    }
+}
+
+fn power_of_two(exponent: usize) -> u128 {
+    if exponent < 128 { 1 << exponent } else { u128::MAX }
+}
+
+// Every output of a system in one manager, over all its inputs in system
+// order. Wires are built once and shared by everything that reads them
+pub fn system_bdd(system: &System) -> (Bdd, Vec<usize>) {
+    let mut bdd = Bdd::new(system_variables(system));
+    let (outputs, _) = bdd.build_system(system);
+    (bdd, outputs)
+}
+
+impl Bdd {
+    // The outputs of a system, and where each one is a don't-care (FALSE if
+    // it has no `dc`). Every input of the system must be one of `vars`
+    pub fn build_system(&mut self, system: &System) -> (Vec<usize>, Vec<usize>) {
+        let mut wires = HashMap::new();
+        for definition in &system.definitions {
+            let f = self.build(&definition.expr, &wires);
+            wires.insert(definition.name.clone(), f);
+        }
+        system.outputs.iter()
+            .map(|output| {
+                let f = self.build(&output.expr, &wires);
+                let dont_care = match &output.dont_care {
+                    Some(dont_care) => self.build(dont_care, &wires),
+                    None => FALSE,
+                };
+                (f, dont_care)
+            })
+            .unzip()
+    }
+
+    // Value of `f` with variable i set to `inputs[i]`
+    pub fn evaluate(&self, f: usize, inputs: &[bool]) -> bool {
+        let mut id = f;
+        while id > TRUE {
+            let node = self.nodes[id];
+            id = if inputs[node.var] { node.high } else { node.low };
+        }
+        id == TRUE
+    }
+}
+
+// `A=1 B=0`, with a note when the other variables are free
+fn assignment_text(bdd: &Bdd, assignment: &[(usize, bool)]) -> String {
+    let mut text = assignment.iter()
+        .map(|&(var, value)| format!("{}={}", bdd.vars[var], value as u8))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if assignment.is_empty() {
+        text = "any assignment".to_string();
+    } else if assignment.len() < bdd.vars.len() {
+        text.push_str(" (the rest either way)");
+    }
+    text
+}
+
+fn count_text(count: u128, n: usize) -> String {
+    if count == u128::MAX {
+        return "at least 2^128".to_string();
+    }
+    let share = count as f64 / 2f64.powi(n as i32) * 100.0;
+    if n < 128 {
+        format!("{} of {} ({:.2}%)", count, power_of_two(n), share)
+    } else {
+        format!("{} of 2^{} ({:.2}%)", count, n, share)
+    }
+}
+
+// Text for the Analysis tab: satisfiability, model counts, tautologies and
+// equal outputs, all read off the BDD without building the truth table. The
+// don't-care conditions are not taken into account
+pub fn analysis(system: &System) -> String {
+    let (mut bdd, outputs) = system_bdd(system);
+    let n = bdd.vars.len();
+    let mut lines = vec![
+        format!("Variable order ({}): {}", n, bdd.vars.join(", ")),
+        format!("Shared BDD: {} nodes for {} outputs", bdd.size(&outputs), outputs.len()),
+        String::new(),
+    ];
+
+    for (output, &f) in system.outputs.iter().zip(&outputs) {
+        lines.push(output.name.clone());
+        let size = bdd.size(&[f]);
+        lines.push(format!("  BDD size       {} node{}", size, if size == 1 { "" } else { "s" }));
+        lines.push(match bdd.satisfy_one(f) {
+            Some(assignment) => format!("  Satisfiable    yes, e.g. {}", assignment_text(&bdd, &assignment)),
+            None => "  Satisfiable    no".to_string(),
+        });
+        lines.push(format!("  Models         {}", count_text(bdd.count_models(f), n)));
+        let not_f = bdd.not(f);
+        lines.push(match bdd.satisfy_one(not_f) {
+            Some(assignment) => format!("  Tautology      no, 0 at {}", assignment_text(&bdd, &assignment)),
+            None => "  Tautology      yes".to_string(),
+        });
+        lines.push(String::new());
+    }
+
+    if outputs.len() > 1 {
+        let mut pairs = Vec::new();
+        for a in 0..outputs.len() {
+            for b in a + 1..outputs.len() {
+                if outputs[a] == outputs[b] {
+                    pairs.push(format!("{} = {}", system.outputs[a].name, system.outputs[b].name));
+                }
+            }
+        }
+        lines.push(format!(
+            "Equivalent outputs: {}",
+            if pairs.is_empty() { "none".to_string() } else { pairs.join(", ") }
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{eval, eval_wires};
+    use crate::parser::{parse_system, Dialect};
+
+    const SOURCES: [&str; 5] = [
+        "F = A & B | C & D | E & G",
+        "F = A ^ B ^ C ^ D",
+        "let w = A !& B; F = w <-> (C !| A); G = (A -> D) & !w",
+        "F = A & (B | !C) -> D ^ (E <-> A)",
+        "F = A & !A; G = 1",
+    ];
+
+    // Every output on every row of the truth table, in the system's input order
+    fn truth_table(system: &System) -> Vec<Vec<bool>> {
+        let vars = system_variables(system);
+        (0..1usize << vars.len())
+            .map(|row| {
+                let mut map: HashMap<String, bool> = vars.iter().enumerate()
+                    .map(|(i, var)| (var.clone(), row >> i & 1 == 1))
+                    .collect();
+                eval_wires(system, &mut map);
+                system.outputs.iter().map(|output| eval(&output.expr, &map)).collect()
+            })
+            .collect()
+    }
+
+    // Checks `roots` in `bdd`, whose variable p is input `order[p]`
+    fn check(bdd: &Bdd, roots: &[usize], order: &[usize], table: &[Vec<bool>], source: &str) {
+        let n = order.len();
+        for (o, &root) in roots.iter().enumerate() {
+            let ones = table.iter().filter(|row| row[o]).count();
+            assert_eq!(bdd.count_models(root), ones as u128, "{}", source);
+            for (row, values) in table.iter().enumerate() {
+                let inputs: Vec<bool> = (0..n).map(|p| row >> order[p] & 1 == 1).collect();
+                assert_eq!(bdd.evaluate(root, &inputs), values[o], "{}", source);
+            }
+        }
+    }
+
+    #[test]
+    fn functions_match_the_truth_table() {
+        for source in SOURCES {
+            let system = parse_system(source, Dialect::Standard).unwrap();
+            let (bdd, roots) = system_bdd(&system);
+            let identity: Vec<usize> = (0..bdd.vars.len()).collect();
+            check(&bdd, &roots, &identity, &truth_table(&system), source);
+        }
+    }
+
+    #[test]
+    fn reordering_keeps_the_functions() {
+        for source in SOURCES {
+            let system = parse_system(source, Dialect::Standard).unwrap();
+            let table = truth_table(&system);
+            let (bdd, roots) = system_bdd(&system);
+            let identity: Vec<usize> = (0..bdd.vars.len()).collect();
+            let reversed: Vec<usize> = identity.iter().rev().copied().collect();
+            let sifted = bdd.sift(&roots, &identity);
+            for order in [reversed, sifted] {
+                let (reordered, new_roots) = bdd.reorder(&roots, &order);
+                check(&reordered, &new_roots, &order, &table, source);
+            }
+        }
+    }
+
+    #[test]
+    fn sifting_never_grows_the_bdd() {
+        for source in SOURCES {
+            let system = parse_system(source, Dialect::Standard).unwrap();
+            let (bdd, roots) = system_bdd(&system);
+            let identity: Vec<usize> = (0..bdd.vars.len()).collect();
+            let (sifted, sifted_roots) = bdd.reorder(&roots, &bdd.sift(&roots, &identity));
+            assert!(sifted.size(&sifted_roots) <= bdd.size(&roots), "{}", source);
+        }
+    }
+
+    #[test]
+    fn equal_functions_share_a_node() {
+        let mut bdd = Bdd::new(vec!["A".to_string(), "B".to_string()]);
+        let (a, b) = (bdd.var(0), bdd.var(1));
+        let ab = bdd.and(a, b);
+        let ba = bdd.and(b, a);
+        assert_eq!(ab, ba);
+        let not_a = bdd.not(a);
+        let not_b = bdd.not(b);
+        let nor = bdd.or(not_a, not_b);
+        assert_eq!(bdd.not(nor), ab);
+        assert_eq!(bdd.xor(ab, ba), FALSE);
+    }
+
+    #[test]
+    fn satisfying_assignments_satisfy() {
+        for source in SOURCES {
+            let system = parse_system(source, Dialect::Standard).unwrap();
+            let (bdd, roots) = system_bdd(&system);
+            for &root in &roots {
+                match bdd.satisfy_one(root) {
+                    Some(assignment) => {
+                        let mut inputs = vec![false; bdd.vars.len()];
+                        for (var, value) in assignment {
+                            inputs[var] = value;
+                        }
+                        assert!(bdd.evaluate(root, &inputs), "{}", source);
+                    }
+                    None => assert_eq!(root, FALSE, "{}", source),
+                }
+            }
+        }
+    }
+}
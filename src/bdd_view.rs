@@ -0,0 +1,246 @@
+use crate::bdd::{system_bdd, Bdd, TRUE};
+use crate::expr::System;
+use ratatui::{
+    layout::Rect,
+    style::Color,
+    symbols,
+    text::Line,
+    widgets::{Block, Borders, canvas::{Canvas, Circle, Context, Rectangle}},
+    Frame,
+};
+
+// Spacing of the drawing, in canvas units
+const ROW_HEIGHT: f64 = 20.0;
+const NODE_WIDTH: f64 = 16.0;
+const LABEL_WIDTH: f64 = 24.0;  // column of variable names on the left
+const NODE_RADIUS: f64 = 2.5;
+
+// Past this many nodes the graph is only counted, not drawn
+const MAX_DRAWN_NODES: usize = 400;
+
+pub struct BddViewer {
+    pub pan_x: f64,
+    pub pan_y: f64,
+    pub zoom: f64,
+    pub selected: usize,      // level of the variable picked to move
+    base: Bdd,                // built in the system's own order
+    base_roots: Vec<usize>,
+    outputs: Vec<String>,
+    order: Vec<usize>,        // variables of `base`, top level first
+    bdd: Bdd,                 // `base` rebuilt in `order`
+    roots: Vec<usize>,
+}
+
+// Where each node of the graph goes
+struct Layout {
+    width: f64,
+    height: f64,
+    positions: Vec<Option<(f64, f64)>>,  // by node id, None if not reachable
+}
+
+impl BddViewer {
+    pub fn new(system: &System) -> Self {
+        let (base, base_roots) = system_bdd(system);
+        let order: Vec<usize> = (0..base.vars.len()).collect();
+        let (bdd, roots) = base.reorder(&base_roots, &order);
+        Self {
+            pan_x: 0.0,
+            pan_y: 0.0,
+            zoom: 1.0,
+            selected: 0,
+            base,
+            base_roots,
+            outputs: system.outputs.iter().map(|o| o.name.clone()).collect(),
+            order,
+            bdd,
+            roots,
+        }
+    }
+
+    fn rebuild(&mut self) {
+        (self.bdd, self.roots) = self.base.reorder(&self.base_roots, &self.order);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.order.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    // Swap the selected variable with its neighbour above or below, and keep
+    // it selected
+    pub fn move_selected(&mut self, down: bool) {
+        let other = if down { self.selected + 1 } else { self.selected.wrapping_sub(1) };
+        if other < self.order.len() {
+            self.order.swap(self.selected, other);
+            self.selected = other;
+            self.rebuild();
+        }
+    }
+
+    pub fn sift(&mut self) {
+        self.order = self.base.sift(&self.base_roots, &self.order);
+        self.rebuild();
+    }
+
+    pub fn reset_order(&mut self) {
+        self.order = (0..self.base.vars.len()).collect();
+        self.rebuild();
+    }
+
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.pan_x -= dx / self.zoom;
+        self.pan_y -= dy / self.zoom;
+    }
+
+    pub fn zoom_in(&mut self) {
+        self.zoom = (self.zoom * 1.1).min(10.0);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.zoom = (self.zoom / 1.1).max(0.1);
+    }
+
+    // Rows from the top: output names, one per variable, then the terminals.
+    // Nodes fill their row in the order a depth-first walk meets them, which
+    // keeps most edges short
+    fn layout(&self) -> Layout {
+        let n = self.bdd.vars.len();
+        let mut rows: Vec<Vec<usize>> = vec![Vec::new(); n + 1];
+        let mut seen = vec![false; self.bdd.nodes.len()];
+        let mut stack: Vec<usize> = self.roots.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            if std::mem::replace(&mut seen[id], true) {
+                continue;
+            }
+            let node = self.bdd.nodes[id];
+            rows[node.var].push(id);
+            if id > TRUE {
+                stack.push(node.high);
+                stack.push(node.low);
+            }
+        }
+        rows[n].sort();
+
+        let widest = rows.iter().map(Vec::len).max().unwrap_or(0).max(self.roots.len());
+        let width = LABEL_WIDTH + widest.max(1) as f64 * NODE_WIDTH;
+        let height = (n + 2) as f64 * ROW_HEIGHT;
+        let mut positions = vec![None; self.bdd.nodes.len()];
+        for (row, ids) in rows.iter().enumerate() {
+            let step = (width - LABEL_WIDTH) / ids.len() as f64;
+            for (i, &id) in ids.iter().enumerate() {
+                positions[id] = Some((LABEL_WIDTH + (i as f64 + 0.5) * step, row_y(row + 1, n)));
+            }
+        }
+        Layout { width, height, positions }
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        let size = self.bdd.size(&self.roots);
+        let order = self.bdd.vars.join(", ");
+        let title = format!(
+            "BDD: {} nodes, order {} (Up/Down pick a variable, '<' '>' move it, 'o' sift, 'n' original order)",
+            size, order
+        );
+        let block = Block::default().borders(Borders::ALL).title(title);
+
+        if size > MAX_DRAWN_NODES {
+            let text = format!(
+                "{} nodes is too many to draw (at most {}). Try sifting ('o') or moving variables to shrink it",
+                size, MAX_DRAWN_NODES
+            );
+            f.render_widget(ratatui::widgets::Paragraph::new(text).block(block), area);
+            return;
+        }
+
+        let layout = self.layout();
+        let n = self.bdd.vars.len();
+        let sizes = self.bdd.level_sizes(&self.roots);
+        // Canvas units per terminal column, to centre labels
+        let char_width = layout.width / area.width.saturating_sub(2).max(1) as f64;
+
+        let canvas = Canvas::default()
+            .block(block)
+            .paint(|ctx| {
+                let transform = |(x, y): (f64, f64)| ((x - self.pan_x) * self.zoom, (y - self.pan_y) * self.zoom);
+                let centred = |x: f64, text: &str| x - text.chars().count() as f64 * char_width / 2.0;
+
+                // Edges first, so the nodes are drawn over them
+                for (id, position) in layout.positions.iter().enumerate() {
+                    let (Some(from), true) = (position, id > TRUE) else { continue };
+                    let node = self.bdd.nodes[id];
+                    if let Some(to) = layout.positions[node.high] {
+                        line(ctx, transform(*from), transform(to), Color::Green);
+                    }
+                    if let Some(to) = layout.positions[node.low] {
+                        dashed_line(ctx, transform(*from), transform(to), Color::Red);
+                    }
+                }
+                for (o, &root) in self.roots.iter().enumerate() {
+                    let x = LABEL_WIDTH + (o as f64 + 0.5) * (layout.width - LABEL_WIDTH) / self.roots.len() as f64;
+                    let label = (x, row_y(0, n));
+                    if let Some(to) = layout.positions[root] {
+                        line(ctx, transform((label.0, label.1 - ROW_HEIGHT / 4.0)), transform(to), Color::Cyan);
+                    }
+                    let (x, y) = transform(label);
+                    ctx.print(centred(x, &self.outputs[o]), y, Line::styled(self.outputs[o].clone(), Color::Cyan));
+                }
+                ctx.layer();
+
+                // The variable of each row, with how many nodes test it
+                for (level, name) in self.bdd.vars.iter().enumerate() {
+                    let (x, y) = transform((0.0, row_y(level + 1, n)));
+                    let color = if level == self.selected { Color::Yellow } else { Color::Gray };
+                    ctx.print(x, y, Line::styled(format!("{} ({})", name, sizes[level]), color));
+                }
+
+                for (id, position) in layout.positions.iter().enumerate() {
+                    let Some(position) = *position else { continue };
+                    let (x, y) = transform(position);
+                    let radius = NODE_RADIUS * self.zoom;
+                    if id <= TRUE {
+                        ctx.draw(&Rectangle {
+                            x: x - radius, y: y - radius, width: 2.0 * radius, height: 2.0 * radius,
+                            color: Color::White,
+                        });
+                        ctx.print(centred(x, "0"), y, if id == TRUE { "1" } else { "0" });
+                    } else {
+                        let node = self.bdd.nodes[id];
+                        let color = if node.var == self.selected { Color::Yellow } else { Color::White };
+                        ctx.draw(&Circle { x, y, radius, color });
+                        let name = &self.bdd.vars[node.var];
+                        ctx.print(centred(x, name), y, Line::styled(name.clone(), color));
+                    }
+                }
+            })
+            .marker(symbols::Marker::Braille)
+            .x_bounds([0.0, layout.width])
+            .y_bounds([0.0, layout.height]);
+
+        f.render_widget(canvas, area);
+    }
+}
+
+// Height of row `row` counted from the top, out of `n` variable rows
+fn row_y(row: usize, n: usize) -> f64 {
+    (n + 1 - row) as f64 * ROW_HEIGHT + ROW_HEIGHT / 2.0
+}
+
+fn line(ctx: &mut Context, (x1, y1): (f64, f64), (x2, y2): (f64, f64), color: Color) {
+    ctx.draw(&ratatui::widgets::canvas::Line { x1, y1, x2, y2, color });
+}
+
+// Low edges: every other stretch of the line
+fn dashed_line(ctx: &mut Context, (x1, y1): (f64, f64), (x2, y2): (f64, f64), color: Color) {
+    let dashes = ((x2 - x1).hypot(y2 - y1) / 3.0).ceil().max(1.0) as usize;
+    for i in (0..dashes).step_by(2) {
+        let at = |t: f64| (x1 + (x2 - x1) * t, y1 + (y2 - y1) * t);
+        let start = at(i as f64 / dashes as f64);
+        let end = at((i + 1) as f64 / dashes as f64);
+        line(ctx, start, end, color);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::expr::{Expr, System};
+use crate::truth_table::{system_variables, variables};
+use std::collections::{HashMap, HashSet};
+
+// One gate of a circuit. Operands are indices of earlier nodes, so the nodes
+// are always in an order where every gate comes after its inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Node {
+    Input(usize),  // index into `Dag::inputs`
+    Const(bool),
+    Not(usize),
+    And(usize, usize),
+    Or(usize, usize),
+    Xor(usize, usize),
+    Nand(usize, usize),
+    Nor(usize, usize),
+    Xnor(usize, usize),
+}
+
+impl Node {
+    pub fn operands(&self) -> Vec<usize> {
+        match *self {
+            Node::Input(_) | Node::Const(_) => Vec::new(),
+            Node::Not(a) => vec![a],
+            Node::And(a, b) | Node::Or(a, b) | Node::Xor(a, b)
+            | Node::Nand(a, b) | Node::Nor(a, b) | Node::Xnor(a, b) => vec![a, b],
+        }
+    }
+}
+
+// A system as a circuit where structurally identical subexpressions are built
+// once and fan out to every use. Operands of the symmetric gates are put in
+// order before looking a node up, so `A & B` and `B & A` are the same gate
+#[derive(Debug, Clone, Default)]
+pub struct Dag {
+    pub inputs: Vec<String>,
+    pub nodes: Vec<Node>,                // inputs first, in the order of `inputs`
+    pub wires: Vec<(String, usize)>,     // node driving each `let` wire
+    pub outputs: Vec<(String, usize)>,   // node driving each output
+    table: HashMap<Node, usize>,
+}
+
+impl Dag {
+    pub fn from_system(system: &System) -> Dag {
+        // Inputs only a don't-care condition reads have nothing to drive
+        let mut used = HashSet::new();
+        for expr in system.definitions.iter().map(|d| &d.expr).chain(system.outputs.iter().map(|o| &o.expr)) {
+            used.extend(variables(expr));
+        }
+        let mut dag = Dag {
+            inputs: system_variables(system).into_iter().filter(|v| used.contains(v)).collect(),
+            ..Dag::default()
+        };
+        for i in 0..dag.inputs.len() {
+            dag.node(Node::Input(i));
+        }
+
+        let mut wires = HashMap::new();
+        for definition in &system.definitions {
+            let id = dag.add(&definition.expr, &wires);
+            wires.insert(definition.name.clone(), id);
+            dag.wires.push((definition.name.clone(), id));
+        }
+        for output in &system.outputs {
+            let id = dag.add(&output.expr, &wires);
+            dag.outputs.push((output.name.clone(), id));
+        }
+        dag
+    }
+
+    // The node for `node`, made only if an identical one doesn't exist yet
+    fn node(&mut self, node: Node) -> usize {
+        let node = match node {
+            Node::And(a, b) => Node::And(a.min(b), a.max(b)),
+            Node::Or(a, b) => Node::Or(a.min(b), a.max(b)),
+            Node::Xor(a, b) => Node::Xor(a.min(b), a.max(b)),
+            Node::Nand(a, b) => Node::Nand(a.min(b), a.max(b)),
+            Node::Nor(a, b) => Node::Nor(a.min(b), a.max(b)),
+            Node::Xnor(a, b) => Node::Xnor(a.min(b), a.max(b)),
+            other => other,
+        };
+        if let Some(&id) = self.table.get(&node) {
+            return id;
+        }
+        self.nodes.push(node);
+        self.table.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    // There are no implication gates, so `A -> B` becomes `!A | B`, and
+    // `A <-> B` an XNOR
+    fn add(&mut self, expr: &Expr, wires: &HashMap<String, usize>) -> usize {
+        match expr {
+            Expr::Var(name) => self.inputs.iter().position(|v| v == name).unwrap(),
+            Expr::Ref(name) => wires[name],
+            Expr::Const(value) => self.node(Node::Const(*value)),
+            Expr::Not(inner) => {
+                let a = self.add(inner, wires);
+                self.node(Node::Not(a))
+            }
+            Expr::Implies(a, b) => {
+                let a = self.add(a, wires);
+                let not_a = self.node(Node::Not(a));
+                let b = self.add(b, wires);
+                self.node(Node::Or(not_a, b))
+            }
+            Expr::And(a, b) | Expr::Or(a, b) | Expr::Xor(a, b)
+            | Expr::Nand(a, b) | Expr::Nor(a, b) | Expr::Xnor(a, b) | Expr::Iff(a, b) => {
+                let (a, b) = (self.add(a, wires), self.add(b, wires));
+                self.node(match expr {
+                    Expr::And(..) => Node::And(a, b),
+                    Expr::Or(..) => Node::Or(a, b),
+                    Expr::Xor(..) => Node::Xor(a, b),
+                    Expr::Nand(..) => Node::Nand(a, b),
+                    Expr::Nor(..) => Node::Nor(a, b),
+                    _ => Node::Xnor(a, b),
+                })
+            }
+        }
+    }
+
+    // Logic gates, leaving out inputs and constants
+    pub fn gate_count(&self) -> usize {
+        self.nodes.iter().filter(|node| !matches!(node, Node::Input(_) | Node::Const(_))).count()
+    }
+
+    // Which nodes `root` depends on, itself included
+    pub fn cone(&self, root: usize) -> Vec<bool> {
+        let mut reached = vec![false; self.nodes.len()];
+        reached[root] = true;
+        for id in (0..=root).rev() {
+            if reached[id] {
+                for operand in self.nodes[id].operands() {
+                    reached[operand] = true;
+                }
+            }
+        }
+        reached
+    }
+
+    // The value of every node, given one value per input
+    pub fn evaluate(&self, inputs: &[bool]) -> Vec<bool> {
+        let mut values: Vec<bool> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let value = match *node {
+                Node::Input(i) => inputs[i],
+                Node::Const(value) => value,
+                Node::Not(a) => !values[a],
+                Node::And(a, b) => values[a] && values[b],
+                Node::Or(a, b) => values[a] || values[b],
+                Node::Xor(a, b) => values[a] ^ values[b],
+                Node::Nand(a, b) => !(values[a] && values[b]),
+                Node::Nor(a, b) => !(values[a] || values[b]),
+                Node::Xnor(a, b) => values[a] == values[b],
+            };
+            values.push(value);
+        }
+        values
+    }
+}
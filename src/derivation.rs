@@ -0,0 +1,575 @@
+use crate::expr::{Expr, System};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+// A derivation stops after this many steps, or once the expression is this
+// many characters long, since distributing can blow it up
+const MAX_STEPS: usize = 200;
+const MAX_LENGTH: usize = 2000;
+
+// Width of the column of law names
+const LAW_WIDTH: usize = 18;
+
+type Path = Vec<usize>;
+
+// One application of a law
+pub struct Step {
+    pub law: &'static str,
+    pub marks: Vec<Path>,  // subterms of the previous expression it rewrote
+    pub expr: Expr,        // the whole expression after it
+}
+
+pub struct Derivation {
+    pub start: Expr,
+    pub steps: Vec<Step>,
+    pub finished: bool,  // false if it was cut short
+}
+
+// A law matched at one subterm: the parts of the subterm it used, relative
+// to it, and what the subterm becomes
+struct Rewrite {
+    law: &'static str,
+    marks: Vec<Path>,
+    result: Expr,
+}
+
+// Every way a law matches a subterm, as the subterm itself, not inside it
+type Rule = fn(&Expr) -> Vec<Rewrite>;
+
+// In the order `derive` tries them
+const RULES: [Rule; 5] = [basic, absorption, definition, de_morgan, distribution];
+
+// Rewrites `expr` one law at a time. Laws that make it smaller are tried
+// first, then the other operators are written with AND, OR and NOT, NOTs are
+// pushed down to the variables and AND is distributed over OR, so whatever
+// is left at the end is a sum of products. The first subterm from the top
+// where a law applies is the one rewritten
+pub fn derive(expr: &Expr) -> Derivation {
+    let mut current = expr.clone();
+    let mut steps = Vec::new();
+    while steps.len() < MAX_STEPS && current.to_string().len() <= MAX_LENGTH {
+        let Some((path, rewrite)) = RULES.iter().find_map(|rule| find(&current, *rule)) else {
+            return Derivation { start: expr.clone(), steps, finished: true };
+        };
+        let marks = rewrite.marks.iter().map(|mark| [path.as_slice(), mark].concat()).collect();
+        current = regroup(&replace(&current, &path, rewrite.result));
+        steps.push(Step { law: rewrite.law, marks, expr: current.clone() });
+    }
+    Derivation { start: expr.clone(), steps, finished: false }
+}
+
+// The first subterm, top down, where `rule` applies, and its path
+fn find(expr: &Expr, rule: Rule) -> Option<(Path, Rewrite)> {
+    subterms(expr).into_iter().find_map(|(path, subterm)| rule(subterm).into_iter().next().map(|rewrite| (path, rewrite)))
+}
+
+// Every subterm with its path, top down. A chain of ANDs or ORs is one
+// subterm however it is bracketed
+fn subterms(expr: &Expr) -> Vec<(Path, &Expr)> {
+    fn walk<'a>(expr: &'a Expr, path: &mut Path, out: &mut Vec<(Path, &'a Expr)>) {
+        out.push((path.clone(), expr));
+        let children: Vec<(Path, &Expr)> = match expr {
+            Expr::And(..) | Expr::Or(..) => operands(expr, matches!(expr, Expr::And(..))),
+            Expr::Not(a) => vec![(vec![0], a)],
+            Expr::Xor(a, b) | Expr::Xnor(a, b) | Expr::Nand(a, b) | Expr::Nor(a, b)
+            | Expr::Implies(a, b) | Expr::Iff(a, b) => vec![(vec![0], a), (vec![1], b)],
+            Expr::Var(_) | Expr::Const(_) | Expr::Ref(_) => Vec::new(),
+        };
+        for (relative, child) in children {
+            let depth = path.len();
+            path.extend(relative);
+            walk(child, path, out);
+            path.truncate(depth);
+        }
+    }
+    let mut out = Vec::new();
+    walk(expr, &mut Vec::new(), &mut out);
+    out
+}
+
+fn replace(expr: &Expr, path: &[usize], with: Expr) -> Expr {
+    let Some((&first, rest)) = path.split_first() else { return with };
+    let child = |e: &Expr, i: usize| if i == first { Box::new(replace(e, rest, with.clone())) } else { Box::new(e.clone()) };
+    match expr {
+        Expr::Not(a) => Expr::Not(child(a, 0)),
+        Expr::And(a, b) => Expr::And(child(a, 0), child(b, 1)),
+        Expr::Or(a, b) => Expr::Or(child(a, 0), child(b, 1)),
+        Expr::Xor(a, b) => Expr::Xor(child(a, 0), child(b, 1)),
+        Expr::Xnor(a, b) => Expr::Xnor(child(a, 0), child(b, 1)),
+        Expr::Nand(a, b) => Expr::Nand(child(a, 0), child(b, 1)),
+        Expr::Nor(a, b) => Expr::Nor(child(a, 0), child(b, 1)),
+        Expr::Implies(a, b) => Expr::Implies(child(a, 0), child(b, 1)),
+        Expr::Iff(a, b) => Expr::Iff(child(a, 0), child(b, 1)),
+        Expr::Var(_) | Expr::Const(_) | Expr::Ref(_) => expr.clone(),
+    }
+}
+
+// Every chain of ANDs or ORs bracketed from the left, as it prints with no
+// brackets, so a chain put in place of an operand of the same kind merges
+// into it
+fn regroup(expr: &Expr) -> Expr {
+    let binary = |a: &Expr, b: &Expr| (Box::new(regroup(a)), Box::new(regroup(b)));
+    match expr {
+        Expr::And(..) | Expr::Or(..) => {
+            let and = matches!(expr, Expr::And(..));
+            join(operands(expr, and).into_iter().map(|(_, e)| regroup(e)).collect(), and)
+        }
+        Expr::Not(a) => not(regroup(a)),
+        Expr::Xor(a, b) => { let (a, b) = binary(a, b); Expr::Xor(a, b) }
+        Expr::Xnor(a, b) => { let (a, b) = binary(a, b); Expr::Xnor(a, b) }
+        Expr::Nand(a, b) => { let (a, b) = binary(a, b); Expr::Nand(a, b) }
+        Expr::Nor(a, b) => { let (a, b) = binary(a, b); Expr::Nor(a, b) }
+        Expr::Implies(a, b) => { let (a, b) = binary(a, b); Expr::Implies(a, b) }
+        Expr::Iff(a, b) => { let (a, b) = binary(a, b); Expr::Iff(a, b) }
+        Expr::Var(_) | Expr::Const(_) | Expr::Ref(_) => expr.clone(),
+    }
+}
+
+// The operands of a chain of ANDs (`and`) or ORs with their paths; anything
+// else is a chain of one
+fn operands(expr: &Expr, and: bool) -> Vec<(Path, &Expr)> {
+    fn walk<'a>(expr: &'a Expr, and: bool, path: &mut Path, out: &mut Vec<(Path, &'a Expr)>) {
+        match (expr, and) {
+            (Expr::And(a, b), true) | (Expr::Or(a, b), false) => {
+                for (i, operand) in [a, b].into_iter().enumerate() {
+                    path.push(i);
+                    walk(operand, and, path, out);
+                    path.pop();
+                }
+            }
+            _ => out.push((path.clone(), expr)),
+        }
+    }
+    let mut out = Vec::new();
+    walk(expr, and, &mut Vec::new(), &mut out);
+    out
+}
+
+fn join(terms: Vec<Expr>, and: bool) -> Expr {
+    if and { Expr::all(terms) } else { Expr::any(terms) }
+}
+
+fn not(expr: Expr) -> Expr {
+    Expr::Not(Box::new(expr))
+}
+
+// Equal for any order of the operands of an AND or OR
+fn key(expr: &Expr) -> String {
+    match expr {
+        Expr::And(..) | Expr::Or(..) => {
+            let and = matches!(expr, Expr::And(..));
+            let mut keys: Vec<String> = operands(expr, and).iter().map(|(_, e)| key(e)).collect();
+            keys.sort();
+            format!("{}({})", if and { "&" } else { "|" }, keys.join(","))
+        }
+        Expr::Not(inner) => format!("!{}", key(inner)),
+        Expr::Xor(a, b) => format!("xor({},{})", key(a), key(b)),
+        Expr::Xnor(a, b) => format!("xnor({},{})", key(a), key(b)),
+        Expr::Nand(a, b) => format!("nand({},{})", key(a), key(b)),
+        Expr::Nor(a, b) => format!("nor({},{})", key(a), key(b)),
+        Expr::Implies(a, b) => format!("implies({},{})", key(a), key(b)),
+        Expr::Iff(a, b) => format!("iff({},{})", key(a), key(b)),
+        Expr::Var(_) | Expr::Const(_) | Expr::Ref(_) => format!("[{}]", expr),
+    }
+}
+
+fn negated(key: &str) -> String {
+    key.strip_prefix('!').map(str::to_string).unwrap_or_else(|| format!("!{}", key))
+}
+
+// The operands of a chain with the operands of each, so for an OR of ANDs
+// the products and their literals
+struct Chain<'a> {
+    and: bool,
+    terms: Vec<(Path, &'a Expr)>,
+    factors: Vec<Vec<(&'a Expr, String)>>,
+}
+
+impl<'a> Chain<'a> {
+    fn new(expr: &'a Expr) -> Option<Self> {
+        let and = match expr {
+            Expr::And(..) => true,
+            Expr::Or(..) => false,
+            _ => return None,
+        };
+        let terms = operands(expr, and);
+        let factors = terms.iter()
+            .map(|(_, term)| operands(term, !and).into_iter().map(|(_, f)| (f, key(f))).collect())
+            .collect();
+        Some(Self { and, terms, factors })
+    }
+
+    fn mark(&self, indices: &[usize]) -> Vec<Path> {
+        indices.iter().map(|&i| self.terms[i].0.clone()).collect()
+    }
+
+    // Every factor of term `i` but `skip_i` is a factor of term `j` other than `skip_j`
+    fn subset(&self, i: usize, skip_i: &str, j: usize, skip_j: &str) -> bool {
+        self.factors[i].iter()
+            .filter(|(_, k)| k != skip_i)
+            .all(|(_, k)| k != skip_j && self.factors[j].iter().any(|(_, other)| other == k))
+    }
+
+    // The chain with term `i` replaced (or dropped) and the terms in `drop` dropped
+    fn rebuild(&self, i: usize, with: Option<Expr>, drop: &[usize]) -> Expr {
+        let mut with = with;
+        let terms = self.terms.iter().enumerate()
+            .filter(|(j, _)| !drop.contains(j))
+            .filter_map(|(j, (_, term))| if j == i { with.take() } else { Some((*term).clone()) })
+            .collect();
+        join(terms, self.and)
+    }
+
+    // Term `i` without the factor with key `skip`
+    fn without(&self, i: usize, skip: &str) -> Expr {
+        let factors = self.factors[i].iter().filter(|(_, k)| k != skip).map(|(f, _)| (*f).clone()).collect();
+        join(factors, !self.and)
+    }
+}
+
+fn pairs(n: usize) -> impl Iterator<Item = (usize, usize)> {
+    (0..n).flat_map(move |i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+}
+
+// Constants, double negation, complement and idempotence
+fn basic(expr: &Expr) -> Vec<Rewrite> {
+    let here = |law, result| vec![Rewrite { law, marks: vec![Vec::new()], result }];
+    if let Expr::Not(inner) = expr {
+        return match inner.as_ref() {
+            Expr::Const(value) => here("Complement", Expr::Const(!value)),
+            Expr::Not(a) => here("Double negation", (**a).clone()),
+            _ => Vec::new(),
+        };
+    }
+
+    let Some(chain) = Chain::new(expr) else { return Vec::new() };
+    let keys: Vec<String> = chain.terms.iter().map(|(_, term)| key(term)).collect();
+    let n = keys.len();
+    let mut found = Vec::new();
+    for (i, (_, term)) in chain.terms.iter().enumerate() {
+        // x & 0 = 0, x | 1 = 1
+        if matches!(term, Expr::Const(v) if *v != chain.and) {
+            found.push(Rewrite { law: "Annulment", marks: chain.mark(&[i]), result: Expr::Const(!chain.and) });
+        }
+    }
+    for (i, (_, term)) in chain.terms.iter().enumerate() {
+        // x & 1 = x, x | 0 = x
+        if matches!(term, Expr::Const(v) if *v == chain.and) {
+            found.push(Rewrite { law: "Identity", marks: chain.mark(&[i]), result: chain.rebuild(i, None, &[]) });
+        }
+    }
+    // x & !x = 0, x | !x = 1
+    for (i, j) in pairs(n).filter(|&(i, j)| i < j && keys[j] == negated(&keys[i])) {
+        found.push(Rewrite { law: "Complement", marks: chain.mark(&[i, j]), result: Expr::Const(!chain.and) });
+    }
+    // x & x = x
+    for (i, j) in pairs(n).filter(|&(i, j)| i < j && keys[i] == keys[j]) {
+        found.push(Rewrite { law: "Idempotence", marks: chain.mark(&[i, j]), result: chain.rebuild(j, None, &[]) });
+    }
+    found
+}
+
+// Laws that drop a term or a literal from a chain: absorption, combining,
+// simplification and consensus, and for an AND of ORs, taking out a shared
+// term
+fn absorption(expr: &Expr) -> Vec<Rewrite> {
+    let Some(chain) = Chain::new(expr) else { return Vec::new() };
+    let n = chain.terms.len();
+    let mut found = Vec::new();
+
+    // x | x & y = x
+    for (i, j) in pairs(n).filter(|&(i, j)| chain.subset(i, "", j, "")) {
+        found.push(Rewrite { law: "Absorption", marks: chain.mark(&[i, j]), result: chain.rebuild(j, None, &[]) });
+    }
+
+    // Pairs of terms and a factor of the first that is negated in the second
+    let opposed: Vec<(usize, usize, String)> = pairs(n)
+        .flat_map(|(i, j)| {
+            let chain = &chain;
+            chain.factors[i].iter()
+                .filter(move |(_, k)| chain.factors[j].iter().any(|(_, other)| *other == negated(k)))
+                .map(move |(_, k)| (i, j, k.clone()))
+        })
+        .collect();
+
+    // x & y | x & !y = x
+    for (i, j, k) in &opposed {
+        let not_k = negated(k);
+        if chain.subset(*i, k, *j, &not_k) && chain.subset(*j, &not_k, *i, k) {
+            let result = chain.rebuild(*i, Some(chain.without(*i, k)), &[*j]);
+            found.push(Rewrite { law: "Combining", marks: chain.mark(&[*i, *j]), result });
+        }
+    }
+    // x | !x & y = x | y
+    for (i, j, k) in &opposed {
+        let not_k = negated(k);
+        if chain.subset(*i, k, *j, &not_k) {
+            let result = chain.rebuild(*j, Some(chain.without(*j, &not_k)), &[]);
+            found.push(Rewrite { law: "Simplification", marks: chain.mark(&[*i, *j]), result });
+        }
+    }
+    // x & y | !x & z | y & z = x & y | !x & z
+    if n <= 64 {
+        for (i, j, k) in &opposed {
+            let not_k = negated(k);
+            let consensus: Vec<&String> = chain.factors[*i].iter().filter(|(_, f)| f != k)
+                .chain(chain.factors[*j].iter().filter(|(_, f)| *f != not_k))
+                .map(|(_, f)| f)
+                .collect();
+            let covered = (0..n).filter(|&c| {
+                c != *i && c != *j && consensus.iter().all(|f| chain.factors[c].iter().any(|(_, g)| g == *f))
+            });
+            for c in covered {
+                found.push(Rewrite { law: "Consensus", marks: chain.mark(&[*i, *j, c]), result: chain.rebuild(c, None, &[]) });
+            }
+        }
+    }
+
+    // (x | y) & (x | z) = x | y & z
+    if !chain.and {
+        return found;
+    }
+    let shares = |i: usize, j: usize, k: &String| {
+        chain.factors[i].iter().any(|(_, a)| a == k) && chain.factors[j].iter().any(|(_, b)| b == k)
+    };
+    for (i, j) in pairs(n) {
+        if i > j || chain.factors[i].len() < 2 || chain.factors[j].len() < 2
+            || !chain.factors[i].iter().any(|(_, k)| shares(i, j, k))
+        {
+            continue;
+        }
+        let common: Vec<Expr> = chain.factors[i].iter().filter(|(_, k)| shares(i, j, k)).map(|(f, _)| (*f).clone()).collect();
+        let rest = |t: usize| {
+            let factors: Vec<Expr> = chain.factors[t].iter()
+                .filter(|(_, k)| !shares(i, j, k))
+                .map(|(f, _)| (*f).clone())
+                .collect();
+            Expr::any(factors)
+        };
+        let factored = Expr::Or(Box::new(Expr::any(common)), Box::new(Expr::And(Box::new(rest(i)), Box::new(rest(j)))));
+        found.push(Rewrite { law: "Distribution", marks: chain.mark(&[i, j]), result: chain.rebuild(i, Some(factored), &[j]) });
+    }
+    found
+}
+
+// The other operators written with AND, OR and NOT
+fn definition(expr: &Expr) -> Vec<Rewrite> {
+    let both = |a: &Expr, b: &Expr, na: bool, nb: bool| {
+        let literal = |e: &Expr, negate: bool| if negate { not(e.clone()) } else { e.clone() };
+        Expr::And(Box::new(literal(a, na)), Box::new(literal(b, nb)))
+    };
+    let either = |x: Expr, y: Expr| Expr::Or(Box::new(x), Box::new(y));
+    let (law, result) = match expr {
+        Expr::Xor(a, b) => ("Definition of XOR", either(both(a, b, false, true), both(a, b, true, false))),
+        Expr::Xnor(a, b) => ("Definition of XNOR", either(both(a, b, false, false), both(a, b, true, true))),
+        Expr::Iff(a, b) => ("Biconditional", either(both(a, b, false, false), both(a, b, true, true))),
+        Expr::Implies(a, b) => ("Implication", either(not((**a).clone()), (**b).clone())),
+        Expr::Nand(a, b) => ("Definition of NAND", not(Expr::And(a.clone(), b.clone()))),
+        Expr::Nor(a, b) => ("Definition of NOR", not(Expr::Or(a.clone(), b.clone()))),
+        _ => return Vec::new(),
+    };
+    vec![Rewrite { law, marks: vec![Vec::new()], result }]
+}
+
+// !(x & y) = !x | !y, !(x | y) = !x & !y, for chains of any length
+fn de_morgan(expr: &Expr) -> Vec<Rewrite> {
+    let Expr::Not(inner) = expr else { return Vec::new() };
+    let Some(chain) = Chain::new(inner) else { return Vec::new() };
+    let negated = chain.terms.iter().map(|(_, term)| not((*term).clone())).collect();
+    vec![Rewrite { law: "De Morgan", marks: vec![Vec::new()], result: join(negated, !chain.and) }]
+}
+
+// x & (y | z) = x & y | x & z, over each OR in an AND
+fn distribution(expr: &Expr) -> Vec<Rewrite> {
+    let Some(chain) = Chain::new(expr).filter(|chain| chain.and) else { return Vec::new() };
+    (0..chain.terms.len())
+        .filter(|&i| chain.factors[i].len() > 1)
+        .map(|i| {
+            let products: Vec<Expr> = chain.factors[i].iter()
+                .map(|(summand, _)| chain.rebuild(i, Some((*summand).clone()), &[]))
+                .collect();
+            Rewrite { law: "Distribution", marks: vec![Vec::new()], result: Expr::any(products) }
+        })
+        .collect()
+}
+
+// The law that turns one expression into the other in a single step, applied
+// either way round, if the rewrite rules know one. Operands of an AND or OR
+// can be in any order
+pub fn law_between(first: &Expr, second: &Expr) -> Option<&'static str> {
+    if first.to_string() == second.to_string() {
+        return Some("No change");
+    }
+    let (first_key, second_key) = (key(first), key(second));
+    if first_key == second_key {
+        let regrouped = regroup(first).to_string() == regroup(second).to_string();
+        return Some(if regrouped { "Associativity" } else { "Commutativity" });
+    }
+    let one_step = |from: &Expr, to: &str| {
+        subterms(from).into_iter().find_map(|(path, subterm)| {
+            RULES.iter()
+                .flat_map(|rule| rule(subterm))
+                .find(|rewrite| key(&replace(from, &path, rewrite.result.clone())) == to)
+                .map(|rewrite| rewrite.law)
+        })
+    };
+    one_step(first, &second_key).or_else(|| one_step(second, &first_key))
+}
+
+// Every output's derivation, one expression per line with the law that led to
+// it on the left and the subterms the next law rewrites highlighted
+pub fn derivation_lines(system: &System) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for output in &system.outputs {
+        let derivation = derive(&system.inline(&output.expr));
+        lines.push(Line::styled(format!("{}:", output.name), Style::default().fg(Color::Cyan)));
+
+        let exprs = std::iter::once(&derivation.start).chain(derivation.steps.iter().map(|step| &step.expr));
+        for (row, expr) in exprs.enumerate() {
+            let (law, sign) = match row {
+                0 => ("", ' '),
+                _ => (derivation.steps[row - 1].law, '='),
+            };
+            let marks = derivation.steps.get(row).map(|step| step.marks.as_slice()).unwrap_or(&[]);
+            lines.push(step_line(Span::raw(law), sign, expr, marks));
+        }
+
+        let note = match (derivation.finished, derivation.steps.len()) {
+            (true, 0) => "  No law applies".to_string(),
+            (true, 1) => "  1 step".to_string(),
+            (true, steps) => format!("  {} steps", steps),
+            (false, MAX_STEPS) => format!("  Stopped after {} steps", MAX_STEPS),
+            (false, steps) => format!("  Stopped after {} steps, the expression was getting too long", steps),
+        };
+        lines.push(Line::styled(note, Style::default().fg(Color::Gray)));
+        lines.push(Line::from(""));
+    }
+    lines
+}
+
+// One line of a chain of equal expressions: the law in a column on the left,
+// then `=` (or `sign`) and the expression with the subterms at `marks`
+// highlighted
+pub fn step_line(law: Span<'static>, sign: char, expr: &Expr, marks: &[Path]) -> Line<'static> {
+    let padding = LAW_WIDTH.saturating_sub(law.content.chars().count());
+    let mut spans = vec![Span::raw("  "), law, Span::raw(format!("{} {} ", " ".repeat(padding), sign))];
+    let (text, ranges) = expr.marked(marks);
+    let mut at = 0;
+    for range in ranges {
+        spans.push(Span::raw(text[at..range.start].to_string()));
+        spans.push(Span::styled(
+            text[range.clone()].to_string(),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ));
+        at = range.end;
+    }
+    spans.push(Span::raw(text[at..].to_string()));
+    Line::from(spans)
+}
+
+// Lines as plain text, to print or paste
+pub fn plain_text(lines: &[Line]) -> String {
+    lines.iter()
+        .map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::eval;
+    use crate::parser::{parse_system, Dialect};
+    use crate::testing::random;
+    use std::collections::HashMap;
+
+    const VARS: [&str; 4] = ["A", "B", "C", "D"];
+
+    fn random_expr(seed: &mut u64, depth: usize) -> Expr {
+        let pair = |seed: &mut u64| (Box::new(random_expr(seed, depth - 1)), Box::new(random_expr(seed, depth - 1)));
+        match if depth == 0 { random(seed, 2) } else { 2 + random(seed, 9) } {
+            0 => Expr::Var(VARS[random(seed, VARS.len())].to_string()),
+            1 => Expr::Const(random(seed, 2) == 0),
+            2 => Expr::Not(Box::new(random_expr(seed, depth - 1))),
+            3 => { let (a, b) = pair(seed); Expr::And(a, b) }
+            4 => { let (a, b) = pair(seed); Expr::Or(a, b) }
+            5 => { let (a, b) = pair(seed); Expr::Xor(a, b) }
+            6 => { let (a, b) = pair(seed); Expr::Xnor(a, b) }
+            7 => { let (a, b) = pair(seed); Expr::Nand(a, b) }
+            8 => { let (a, b) = pair(seed); Expr::Nor(a, b) }
+            9 => { let (a, b) = pair(seed); Expr::Implies(a, b) }
+            _ => { let (a, b) = pair(seed); Expr::Iff(a, b) }
+        }
+    }
+
+    fn truth_table(expr: &Expr) -> Vec<bool> {
+        (0..1usize << VARS.len())
+            .map(|row| {
+                let vars: HashMap<String, bool> = VARS.iter().enumerate()
+                    .map(|(i, v)| (v.to_string(), row >> i & 1 == 1))
+                    .collect();
+                eval(expr, &vars)
+            })
+            .collect()
+    }
+
+    fn is_sum_of_products(expr: &Expr) -> bool {
+        match expr {
+            Expr::Or(a, b) => is_sum_of_products(a) && is_sum_of_products(b),
+            Expr::And(a, b) => !matches!(a.as_ref(), Expr::Or(..)) && !matches!(b.as_ref(), Expr::Or(..))
+                && is_sum_of_products(a) && is_sum_of_products(b),
+            Expr::Not(inner) => matches!(inner.as_ref(), Expr::Var(_)),
+            Expr::Var(_) | Expr::Const(_) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn every_step_keeps_the_truth_table() {
+        let mut seed = 0x4f1bbcdcbfa53e0b;
+        for _ in 0..300 {
+            let depth = 1 + random(&mut seed, 4);
+            let expr = random_expr(&mut seed, depth);
+            let expected = truth_table(&expr);
+            let derivation = derive(&expr);
+            let mut previous = &expr;
+            for step in &derivation.steps {
+                assert_eq!(truth_table(&step.expr), expected, "{} = {} by {}", previous, step.expr, step.law);
+                previous = &step.expr;
+            }
+            if derivation.finished {
+                assert!(is_sum_of_products(previous), "{} ends in {}", expr, previous);
+            }
+        }
+    }
+
+    // What the tab shows, read back line by line
+    #[test]
+    fn printed_steps_parse_back_to_the_same_function() {
+        let sources = [
+            "F = A ^ B ^ C",
+            "F = !(A & B) | (C -> D)",
+            "let w = A !| B; F = w <-> C; G = w & (A | 1)",
+            "F = A & (B | !B)",
+        ];
+        for source in sources {
+            let system = parse_system(source, Dialect::Standard).unwrap();
+            let text = plain_text(&derivation_lines(&system));
+            let mut lines = text.lines();
+            for output in &system.outputs {
+                let expected = truth_table(&system.inline(&output.expr));
+                assert_eq!(lines.next(), Some(format!("{}:", output.name).as_str()));
+                for _ in 0..=derive(&system.inline(&output.expr)).steps.len() {
+                    let line = lines.next().unwrap();
+                    let printed = line.split_once(" = ").map_or(line.trim(), |(_, expr)| expr);
+                    let read = parse_system(&format!("F = {}", printed), Dialect::Standard).unwrap();
+                    assert_eq!(truth_table(&read.outputs[0].expr), expected, "{}: {}", source, line);
+                }
+                lines.next();
+                lines.next();
+            }
+        }
+    }
+}
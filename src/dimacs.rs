@@ -0,0 +1,294 @@
+use crate::espresso::{espresso, output_covers, MAX_CUBES, MAX_CUBE_VARIABLES};
+use crate::expr::{Expr, Output, System};
+use crate::sat::{Cnf, Encoding};
+use crate::truth_table::output_variables;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct DimacsError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DimacsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for DimacsError {}
+
+// A CNF over the inputs alone, one clause per cube of a minimal cover of the
+// 0s, so there are no extra variables but it can be much longer than Tseitin
+pub fn plain_cnf(system: &System, output: usize) -> Result<Encoding, String> {
+    let output = &system.outputs[output];
+    let inputs = output_variables(system, output);
+    let n = inputs.len();
+    if n > MAX_CUBE_VARIABLES {
+        return Err(format!("at most {} inputs are supported for a plain CNF", MAX_CUBE_VARIABLES));
+    }
+
+    let dont_care = output.dont_care.as_ref().map(|dont_care| system.inline(dont_care));
+    let [_, dont_cares, off] = output_covers(&system.inline(&output.expr), dont_care.as_ref(), &inputs)
+        .ok_or_else(|| format!("the output needs more than {} product terms for a plain CNF", MAX_CUBES))?;
+    let off = espresso(n, &off, &dont_cares);
+
+    // A cube of 0s is ruled out by the clause of its negated literals
+    let clauses = off.iter()
+        .map(|cube| {
+            (0..n)
+                .filter(|&i| cube.mask & (1 << (n - 1 - i)) == 0)
+                .map(|i| if cube.value & (1 << (n - 1 - i)) != 0 { -(i as i32 + 1) } else { i as i32 + 1 })
+                .collect()
+        })
+        .collect();
+    Ok(Encoding { cnf: Cnf { num_vars: n, clauses }, inputs })
+}
+
+// DIMACS text, with a `c <number> <name>` comment for every input
+pub fn write_dimacs(encoding: &Encoding) -> String {
+    let mut lines: Vec<String> = encoding.inputs.iter().enumerate()
+        .map(|(i, name)| format!("c {} {}", i + 1, name))
+        .collect();
+    let (first_gate, last) = (encoding.inputs.len() + 1, encoding.cnf.num_vars);
+    if first_gate <= last {
+        lines.push(format!("c variables {} to {} are Tseitin gate outputs", first_gate, last));
+    }
+    lines.push(format!("p cnf {} {}", encoding.cnf.num_vars, encoding.cnf.clauses.len()));
+    for clause in &encoding.cnf.clauses {
+        let literals: Vec<String> = clause.iter().map(i32::to_string).collect();
+        lines.push(format!("{} 0", literals.join(" ")));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+// A name the parser reads back as a variable: letters and digits, starting
+// with a letter, and not a constant or keyword
+fn is_name(word: &str) -> bool {
+    word.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && word.chars().all(|c| c.is_ascii_alphanumeric())
+        && !["true", "false"].contains(&word.to_ascii_lowercase().as_str())
+        && !["let", "dc"].contains(&word)
+}
+
+// `join` over all terms as a balanced tree, so a file with many clauses does
+// not make a chain deep enough to overflow the stack when walked
+fn balanced(mut terms: Vec<Expr>, join: fn(Box<Expr>, Box<Expr>) -> Expr, empty: Expr) -> Expr {
+    match terms.len() {
+        0 => empty,
+        1 => terms.pop().unwrap(),
+        len => {
+            let right = terms.split_off(len / 2);
+            join(Box::new(balanced(terms, join, empty.clone())), Box::new(balanced(right, join, empty)))
+        }
+    }
+}
+
+// Reads a DIMACS CNF file into a single output F, an AND of ORs. Variables are
+// named by `c <number> <name>` comments where there are any, x1, x2... otherwise,
+// and are all inputs in numeric order, used or not
+pub fn parse_dimacs(text: &str) -> Result<System, DimacsError> {
+    let mut header: Option<(usize, usize)> = None;
+    let mut names: HashMap<usize, String> = HashMap::new();
+    let mut clauses: Vec<Vec<i32>> = Vec::new();
+    let mut clause: Vec<i32> = Vec::new();
+    let mut last_line = 0;
+
+    for (number, raw) in text.lines().enumerate() {
+        let line_number = number + 1;
+        last_line = line_number;
+        let error = |message: String| DimacsError { line: line_number, message };
+        let line = raw.trim();
+        let mut words = line.split_whitespace();
+
+        match words.next() {
+            None => continue,
+            Some("c") => {
+                if let (Some(var), Some(name), None) = (words.next(), words.next(), words.next())
+                    && let (Ok(var), true) = (var.parse::<usize>(), is_name(name))
+                {
+                    names.insert(var, name.to_string());
+                }
+            }
+            Some("p") => {
+                let fields: Vec<&str> = words.collect();
+                let [format, vars, count] = fields[..] else {
+                    return Err(error("expected `p cnf <variables> <clauses>`".to_string()));
+                };
+                match (format, vars.parse(), count.parse()) {
+                    ("cnf", Ok(vars), Ok(count)) => header = Some((vars, count)),
+                    _ => return Err(error("expected `p cnf <variables> <clauses>`".to_string())),
+                }
+            }
+            // Some benchmark sets end the clauses with `%`
+            Some("%") => break,
+            Some(first) => {
+                let Some((vars, _)) = header else {
+                    return Err(error("clause before the `p cnf` line".to_string()));
+                };
+                for word in std::iter::once(first).chain(words) {
+                    let literal: i32 = word.parse().map_err(|_| error(format!("`{}` is not a literal", word)))?;
+                    if literal == 0 {
+                        clauses.push(std::mem::take(&mut clause));
+                    } else if literal.unsigned_abs() as usize > vars {
+                        return Err(error(format!("variable {} is past the {} declared", literal.unsigned_abs(), vars)));
+                    } else {
+                        clause.push(literal);
+                    }
+                }
+            }
+        }
+    }
+
+    let Some((vars, count)) = header else {
+        return Err(DimacsError { line: 1, message: "missing `p cnf` line".to_string() });
+    };
+    // The last clause may leave out its 0
+    if !clause.is_empty() {
+        clauses.push(clause);
+    }
+    if clauses.len() != count {
+        return Err(DimacsError {
+            line: last_line,
+            message: format!("{} clauses declared but {} found", count, clauses.len()),
+        });
+    }
+
+    let inputs: Vec<String> = (1..=vars)
+        .map(|var| names.get(&var).cloned().unwrap_or_else(|| format!("x{}", var)))
+        .collect();
+    let mut seen = HashSet::new();
+    if let Some(name) = inputs.iter().find(|name| !seen.insert(name.as_str())) {
+        return Err(DimacsError { line: 1, message: format!("`{}` names two variables", name) });
+    }
+    let literal = |l: &i32| {
+        let var = Expr::Var(inputs[l.unsigned_abs() as usize - 1].clone());
+        if *l < 0 { Expr::Not(Box::new(var)) } else { var }
+    };
+    let clauses = clauses.iter()
+        .map(|clause| balanced(clause.iter().map(literal).collect(), Expr::Or, Expr::Const(false)))
+        .collect();
+    let expr = balanced(clauses, Expr::And, Expr::Const(true));
+    let name = ["F".to_string(), "OUT".to_string()].into_iter()
+        .chain((1..).map(|i| format!("OUT{}", i)))
+        .find(|name| !seen.contains(name.as_str()))
+        .unwrap();
+    let output = Output { name, expr, dont_care: None };
+    Ok(System { inputs, definitions: Vec::new(), outputs: vec![output] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{eval, Logic};
+    use crate::parser::{parse_system, Dialect};
+    use crate::sat::tseitin;
+    use crate::testing::random;
+    use crate::truth_table::output_values;
+
+    fn message(text: &str) -> String {
+        parse_dimacs(text).map(|_| ()).unwrap_err().to_string()
+    }
+
+    // Rows where the output is not a don't-care keep their value
+    #[test]
+    fn plain_cnf_reads_back_as_the_truth_table() {
+        let sources = [
+            "F = A & B | !C",
+            "F = (A ^ B) & (A ^ B ^ C) | !(A -> D)",
+            "let w = A !& B; F = w <-> (C !| A)",
+            "F = A ^ B ^ C; dc F = A & B",
+            "F = A & !A",
+            "F = A | !A",
+        ];
+        for source in sources {
+            let system = parse_system(source, Dialect::Standard).unwrap();
+            let output = &system.outputs[0];
+            let vars = output_variables(&system, output);
+            let read = parse_dimacs(&write_dimacs(&plain_cnf(&system, 0).unwrap())).unwrap();
+            assert_eq!(read.inputs, vars, "{}", source);
+
+            let expected = output_values(&system, output, &vars);
+            let found = output_values(&read, &read.outputs[0], &vars);
+            for (row, (expected, found)) in expected.iter().zip(&found).enumerate() {
+                if *expected != Logic::DontCare {
+                    assert_eq!(expected, found, "{} row {}", source, row);
+                }
+            }
+        }
+    }
+
+    // Gate variables come back as x<number>, and every assignment satisfies
+    // the read formula exactly when it satisfies the clauses written
+    #[test]
+    fn tseitin_clauses_read_back_the_same() {
+        for source in ["F = A & B | !C", "let w = A !& B; F = w <-> (C !| A)", "F = 1"] {
+            let system = parse_system(source, Dialect::Standard).unwrap();
+            let encoding = tseitin(&system, 0);
+            let read = parse_dimacs(&write_dimacs(&encoding)).unwrap();
+            assert_eq!(read.inputs[..encoding.inputs.len()], encoding.inputs[..]);
+            assert_eq!(read.inputs.len(), encoding.cnf.num_vars);
+
+            for row in 0..1usize << encoding.cnf.num_vars {
+                let model: Vec<bool> = (0..encoding.cnf.num_vars).map(|v| row >> v & 1 == 1).collect();
+                let satisfied = encoding.cnf.clauses.iter()
+                    .all(|clause| clause.iter().any(|&l| model[l.unsigned_abs() as usize - 1] == (l > 0)));
+                let vars = read.inputs.iter().cloned().zip(model).collect();
+                assert_eq!(eval(&read.outputs[0].expr, &vars), satisfied, "{} row {}", source, row);
+            }
+        }
+    }
+
+    #[test]
+    fn header_and_clause_errors() {
+        assert_eq!(message("1 2 0\n"), "line 1: clause before the `p cnf` line");
+        assert_eq!(message("c nothing\n"), "line 1: missing `p cnf` line");
+        assert_eq!(message("p cnf 2\n"), "line 1: expected `p cnf <variables> <clauses>`");
+        assert_eq!(message("p dnf 2 1\n1 0\n"), "line 1: expected `p cnf <variables> <clauses>`");
+        assert_eq!(message("p cnf 2 1\n1 3 0\n"), "line 2: variable 3 is past the 2 declared");
+        assert_eq!(message("p cnf 2 1\n1 a 0\n"), "line 2: `a` is not a literal");
+        assert_eq!(message("p cnf 2 2\n1 0\n"), "line 2: 2 clauses declared but 1 found");
+        assert_eq!(message("p cnf 2 1\n1 0\n2 0\n"), "line 3: 1 clauses declared but 2 found");
+        // The last clause may leave out its 0
+        assert!(parse_dimacs("p cnf 2 2\n1 0\n-2").is_ok());
+    }
+
+    #[test]
+    fn names_come_from_comments() {
+        let read = parse_dimacs("c 1 Carry\nc 3 sum2\np cnf 3 1\n1 -3 0\n").unwrap();
+        assert_eq!(read.inputs, ["Carry", "x2", "sum2"]);
+        assert_eq!(read.outputs[0].expr.to_string(), "Carry | !sum2");
+
+        // Names the parser would read as something else are left out
+        let read = parse_dimacs("c 1 true\nc 2 FALSE\nc 3 let\nc 4 dc\nc 5 a_b\nc 6 2x\np cnf 6 0\n").unwrap();
+        assert_eq!(read.inputs, ["x1", "x2", "x3", "x4", "x5", "x6"]);
+
+        assert_eq!(message("c 1 A\nc 2 A\np cnf 2 0\n"), "line 1: `A` names two variables");
+        assert_eq!(message("c 2 x1\np cnf 2 0\n"), "line 1: `x1` names two variables");
+
+        // The output keeps clear of the inputs
+        assert_eq!(parse_dimacs("c 1 F\np cnf 1 0\n").unwrap().outputs[0].name, "OUT");
+        assert_eq!(parse_dimacs("c 1 F\nc 2 OUT\np cnf 2 0\n").unwrap().outputs[0].name, "OUT1");
+    }
+
+    // A benchmark-sized 3-CNF, deep enough as a chain to overflow the stack
+    #[test]
+    fn many_clauses_make_a_shallow_tree() {
+        let mut seed = 0x6c62272e07bb0142;
+        let (vars, count) = (200, 120_000);
+        let mut text = format!("p cnf {} {}\n", vars, count);
+        for _ in 0..count {
+            for _ in 0..3 {
+                let var = 1 + random(&mut seed, vars) as i32;
+                text += &format!("{} ", if random(&mut seed, 2) == 0 { var } else { -var });
+            }
+            text += "0\n";
+        }
+        let read = parse_dimacs(&text).unwrap();
+        let encoding = tseitin(&read, 0);
+        assert_eq!(encoding.inputs.len(), vars);
+        assert!(!read.outputs[0].expr.to_string().is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+use crate::bdd::{Bdd, FALSE};
+use crate::expr::System;
+use crate::truth_table::{comparison_table, system_variables, MAX_TABLE_VARIABLES};
+
+// Compare two systems output by output, in order. Inputs only one side uses
+// are inputs the other side ignores, and rows where either side is a
+// don't-care are not compared. Gives whether they agree, and the report
+pub fn equivalence(first: &System, second: &System) -> Result<(bool, String), String> {
+    if first.outputs.len() != second.outputs.len() {
+        return Err(format!(
+            "the first has {} outputs and the second {}",
+            first.outputs.len(), second.outputs.len()
+        ));
+    }
+
+    let first_vars = system_variables(first);
+    let second_vars = system_variables(second);
+    let mut vars = first_vars.clone();
+    vars.extend(second_vars.iter().filter(|v| !first_vars.contains(v)).cloned());
+    let n = vars.len();
+
+    let mut bdd = Bdd::new(vars.clone());
+    let (first_outputs, first_dont_cares) = bdd.build_system(first);
+    let (second_outputs, second_dont_cares) = bdd.build_system(second);
+
+    let mut lines = Vec::new();
+    let only = |a: &[String], b: &[String]| a.iter().filter(|v| !b.contains(v)).cloned().collect::<Vec<_>>();
+    for (side, missing) in [("first", only(&first_vars, &second_vars)), ("second", only(&second_vars, &first_vars))] {
+        if !missing.is_empty() {
+            let pronoun = if missing.len() == 1 { "it" } else { "them" };
+            lines.push(format!("Only the {} uses {}, so the other does not depend on {}", side, missing.join(", "), pronoun));
+        }
+    }
+
+    let mut equivalent = true;
+    for o in 0..first.outputs.len() {
+        let names = format!("{} (1) and {} (2)", first.outputs[o].name, second.outputs[o].name);
+        let either_dont_care = bdd.or(first_dont_cares[o], second_dont_cares[o]);
+        let differ = bdd.xor(first_outputs[o], second_outputs[o]);
+        let differ = bdd.ite(either_dont_care, FALSE, differ);
+
+        let Some(assignment) = bdd.satisfy_one(differ) else {
+            lines.push(format!("{} are equivalent", names));
+            continue;
+        };
+        equivalent = false;
+
+        // Variables the BDD leaves free can be anything, so take 0
+        let mut inputs = vec![false; n];
+        for (var, value) in assignment {
+            inputs[var] = value;
+        }
+        let at = vars.iter().zip(&inputs)
+            .map(|(var, &value)| format!("{}={}", var, value as u8))
+            .collect::<Vec<_>>()
+            .join(" ");
+        lines.push(format!(
+            "{} differ, e.g. at {}: the first gives {} and the second {} ({} of the {} rows differ)",
+            names, at,
+            bdd.evaluate(first_outputs[o], &inputs) as u8,
+            bdd.evaluate(second_outputs[o], &inputs) as u8,
+            bdd.count_models(differ),
+            if n < 128 { (1u128 << n).to_string() } else { format!("2^{}", n) },
+        ));
+    }
+
+    lines.insert(0, if equivalent { "Equivalent".to_string() } else { "Not equivalent".to_string() });
+    lines.push(String::new());
+    if n <= MAX_TABLE_VARIABLES {
+        lines.push(comparison_table(first, second, &vars));
+    } else {
+        lines.push(format!("{} inputs is too many for a truth table (at most {})", n, MAX_TABLE_VARIABLES));
+    }
+    Ok((equivalent, lines.join("\n")))
+}
@@ -0,0 +1,570 @@
+use crate::expr::Expr;
+use crate::minimize::Implicant;
+
+// Cubes are `Implicant`s, so this is the most inputs a cube list can have
+pub const MAX_CUBE_VARIABLES: usize = 32;
+
+// Cube lists longer than this are given up on. An XOR of n inputs needs
+// 2^(n-1) cubes, and minimising lists that long takes far too long
+pub const MAX_CUBES: usize = 1000;
+
+// Stop iterating once a pass gains nothing, or after this many passes
+const MAX_PASSES: usize = 20;
+
+// Mask of all `n` variables: the cube that covers everything
+fn full(n: usize) -> usize {
+    (1 << n) - 1
+}
+
+fn universe(n: usize) -> Implicant {
+    Implicant { value: 0, mask: full(n) }
+}
+
+fn intersects(a: &Implicant, b: &Implicant) -> bool {
+    (a.value ^ b.value) & !a.mask & !b.mask == 0
+}
+
+fn intersection(a: &Implicant, b: &Implicant) -> Implicant {
+    let mask = a.mask & b.mask;
+    Implicant { value: (a.value | b.value) & !mask, mask }
+}
+
+// Does `outer` contain every minterm of `inner`?
+fn contains(outer: &Implicant, inner: &Implicant) -> bool {
+    inner.mask & !outer.mask == 0 && (inner.value ^ outer.value) & !outer.mask == 0
+}
+
+// Smallest cube containing all of `cubes`
+fn supercube(cubes: &[Implicant], n: usize) -> Implicant {
+    let ones = cubes.iter().fold(0, |acc, c| acc | c.value | c.mask);
+    let zeros = cubes.iter().fold(0, |acc, c| acc | (!c.value & full(n)));
+    let mask = ones & zeros;
+    Implicant { value: ones & !mask, mask }
+}
+
+fn cost(cover: &[Implicant], n: usize) -> (usize, usize) {
+    (cover.len(), cover.iter().map(|c| c.literals(n)).sum())
+}
+
+// Drop cubes contained in another cube of the list
+fn single_cube_containment(mut cover: Vec<Implicant>) -> Vec<Implicant> {
+    cover.sort_by_key(|c| std::cmp::Reverse(c.mask.count_ones()));
+    cover.dedup();
+    let mut kept: Vec<Implicant> = Vec::new();
+    for cube in cover {
+        if !kept.iter().any(|k| contains(k, &cube)) {
+            kept.push(cube);
+        }
+    }
+    kept
+}
+
+// The part of the cover inside `cube`, with the variables `cube` fixes made free
+fn cofactor(cover: &[Implicant], cube: &Implicant, n: usize) -> Vec<Implicant> {
+    let fixed = !cube.mask & full(n);
+    cover.iter()
+        .filter(|c| intersects(c, cube))
+        .map(|c| Implicant { value: c.value & !fixed, mask: c.mask | fixed })
+        .collect()
+}
+
+// Cofactor by a single variable `bit` set to `value`
+fn cofactor_var(cover: &[Implicant], bit: usize, value: bool, n: usize) -> Vec<Implicant> {
+    let literal = Implicant { value: if value { bit } else { 0 }, mask: full(n) & !bit };
+    cofactor(cover, &literal, n)
+}
+
+// The variable the cover depends on most, preferring ones that appear both
+// plain and complemented. None if every cube is the universe
+fn splitting_variable(cover: &[Implicant], n: usize) -> Option<(usize, bool)> {
+    (0..n)
+        .map(|b| 1 << b)
+        .filter_map(|bit| {
+            let ones = cover.iter().filter(|c| c.mask & bit == 0 && c.value & bit != 0).count();
+            let zeros = cover.iter().filter(|c| c.mask & bit == 0 && c.value & bit == 0).count();
+            (ones + zeros > 0).then_some((bit, ones > 0 && zeros > 0, ones + zeros))
+        })
+        .max_by_key(|&(_, binate, count)| (binate, count))
+        .map(|(bit, binate, _)| (bit, binate))
+}
+
+// Is the cover true everywhere? Unate recursive paradigm: a cover that is
+// unate in every variable is a tautology only if it holds the universe
+pub fn tautology(cover: &[Implicant], n: usize) -> bool {
+    if cover.iter().any(|c| c.mask == full(n)) {
+        return true;
+    }
+    match splitting_variable(cover, n) {
+        Some((bit, true)) => {
+            tautology(&cofactor_var(cover, bit, false, n), n)
+                && tautology(&cofactor_var(cover, bit, true, n), n)
+        }
+        _ => false,
+    }
+}
+
+// Cubes covering exactly the minterms the cover misses
+pub fn complement(cover: &[Implicant], n: usize) -> Vec<Implicant> {
+    complement_within(cover, n, usize::MAX).unwrap()
+}
+
+// The complement, or None as soon as some part of it needs more than `limit`
+// cubes
+fn complement_within(cover: &[Implicant], n: usize, limit: usize) -> Option<Vec<Implicant>> {
+    if cover.is_empty() {
+        return Some(vec![universe(n)]);
+    }
+    if cover.iter().any(|c| c.mask == full(n)) {
+        return Some(Vec::new());
+    }
+
+    // De Morgan on a single cube: one cube per literal, with that literal flipped
+    if let [cube] = cover {
+        let result: Vec<Implicant> = (0..n)
+            .map(|b| 1 << b)
+            .filter(|bit| cube.mask & bit == 0)
+            .map(|bit| Implicant { value: !cube.value & bit, mask: full(n) & !bit })
+            .collect();
+        return (result.len() <= limit).then_some(result);
+    }
+
+    let (bit, _) = splitting_variable(cover, n).expect("a cover without the universe has a literal");
+    let low = complement_within(&cofactor_var(cover, bit, false, n), n, limit)?;
+    let high = complement_within(&cofactor_var(cover, bit, true, n), n, limit)?;
+
+    // Cubes on both sides do not depend on the splitting variable
+    let mut result = Vec::new();
+    for cube in &low {
+        if high.contains(cube) {
+            result.push(*cube);
+        } else {
+            result.push(Implicant { value: cube.value, mask: cube.mask & !bit });
+        }
+    }
+    for cube in &high {
+        if !low.contains(cube) {
+            result.push(Implicant { value: cube.value | bit, mask: cube.mask & !bit });
+        }
+    }
+    let result = single_cube_containment(result);
+    (result.len() <= limit).then_some(result)
+}
+
+// AND of two covers: every pairwise intersection
+fn product(a: &[Implicant], b: &[Implicant]) -> Vec<Implicant> {
+    let cubes = a.iter()
+        .flat_map(|x| b.iter().filter(|y| intersects(x, y)).map(move |y| intersection(x, y)))
+        .collect();
+    single_cube_containment(cubes)
+}
+
+// Sum-of-products cube list of an expression over `vars`, or None if it or
+// any step on the way needs more than MAX_CUBES cubes. Wires must already be
+// inlined
+pub fn expr_cover(expr: &Expr, vars: &[String]) -> Option<Vec<Implicant>> {
+    let n = vars.len();
+    let bounded = |cover: Vec<Implicant>| (cover.len() <= MAX_CUBES).then_some(cover);
+    let cover = |e: &Expr| expr_cover(e, vars);
+    let and = |a: Vec<Implicant>, b: Vec<Implicant>| {
+        // Count the intersections first, since dropping contained cubes from
+        // a list far past the limit would take too long by itself
+        let pairs: usize = a.iter().map(|x| b.iter().filter(|y| intersects(x, y)).count()).sum();
+        if pairs > 4 * MAX_CUBES { None } else { bounded(product(&a, &b)) }
+    };
+    let or = |mut a: Vec<Implicant>, b: Vec<Implicant>| {
+        a.extend(b);
+        bounded(single_cube_containment(a))
+    };
+    let not = |a: Vec<Implicant>| complement_within(&a, n, MAX_CUBES);
+
+    Some(match expr {
+        Expr::Var(name) | Expr::Ref(name) => {
+            let i = vars.iter().position(|v| v == name).expect("variable missing from the list");
+            let bit = 1 << (n - 1 - i);
+            vec![Implicant { value: bit, mask: full(n) & !bit }]
+        }
+        Expr::Const(true) => vec![universe(n)],
+        Expr::Const(false) => Vec::new(),
+        Expr::Not(inner) => not(cover(inner)?)?,
+        Expr::And(a, b) => and(cover(a)?, cover(b)?)?,
+        Expr::Or(a, b) => or(cover(a)?, cover(b)?)?,
+        Expr::Nand(a, b) => not(and(cover(a)?, cover(b)?)?)?,
+        Expr::Nor(a, b) => not(or(cover(a)?, cover(b)?)?)?,
+        Expr::Implies(a, b) => or(not(cover(a)?)?, cover(b)?)?,
+        Expr::Xor(a, b) | Expr::Xnor(a, b) | Expr::Iff(a, b) => {
+            let (a, b) = (cover(a)?, cover(b)?);
+            let (not_a, not_b) = (not(a.clone())?, not(b.clone())?);
+            if matches!(expr, Expr::Xor(..)) {
+                or(and(a, not_b)?, and(not_a, b)?)?
+            } else {
+                or(and(a, b)?, and(not_a, not_b)?)?
+            }
+        }
+    })
+}
+
+// On-set, don't-care and off-set cube lists of an output, or None if any of
+// them needs more than MAX_CUBES cubes. Wires must already be inlined
+pub fn output_covers(expr: &Expr, dont_care: Option<&Expr>, vars: &[String]) -> Option<[Vec<Implicant>; 3]> {
+    let on = expr_cover(expr, vars)?;
+    let dont_cares = match dont_care {
+        Some(dont_care) => expr_cover(dont_care, vars)?,
+        None => Vec::new(),
+    };
+    let mut care = on.clone();
+    care.extend_from_slice(&dont_cares);
+    let off = complement_within(&care, vars.len(), MAX_CUBES)?;
+    Some([on, dont_cares, off])
+}
+
+// Heuristic minimal sum of products of the function that is 1 on `on`, free
+// on `dont_cares` and 0 elsewhere, modelled on Espresso: grow every cube as
+// far as the off-set allows (EXPAND), drop cubes the others already cover
+// (IRREDUNDANT), then shrink each cube to what only it covers (REDUCE) so the
+// next EXPAND can grow it in a better direction. Works on cube lists, never on
+// the truth table
+pub fn espresso(n: usize, on: &[Implicant], dont_cares: &[Implicant]) -> Vec<Implicant> {
+    let on = single_cube_containment(on.to_vec());
+    if on.is_empty() {
+        return Vec::new();
+    }
+
+    let mut care = on.clone();
+    care.extend_from_slice(dont_cares);
+    let off = complement(&care, n);
+    if off.is_empty() {
+        return vec![universe(n)];
+    }
+
+    let mut cover = irredundant(expand(on, &off, n), dont_cares, n);
+    for _ in 0..MAX_PASSES {
+        let candidate = irredundant(expand(reduce(&cover, dont_cares, n), &off, n), dont_cares, n);
+        if cost(&candidate, n) < cost(&cover, n) {
+            cover = candidate;
+            continue;
+        }
+
+        // LAST_GASP: shrink every cube on its own, and keep any regrown cube
+        // that lets IRREDUNDANT drop more than it adds
+        let candidate = last_gasp(&cover, dont_cares, &off, n);
+        if cost(&candidate, n) >= cost(&cover, n) {
+            break;
+        }
+        cover = candidate;
+    }
+
+    cover.sort();
+    cover
+}
+
+// Raise literals of each cube while it stays clear of the off-set, biggest
+// cubes first. Cubes that end up inside an expanded one are dropped
+fn expand(cover: Vec<Implicant>, off: &[Implicant], n: usize) -> Vec<Implicant> {
+    let mut pending = cover;
+    pending.sort_by_key(|c| std::cmp::Reverse(c.mask.count_ones()));
+    let mut result: Vec<Implicant> = Vec::new();
+
+    while !pending.is_empty() {
+        let mut cube = pending.remove(0);
+
+        // Raise one literal at a time, each time the one that swallows the
+        // most other cubes, then the one they disagree with most
+        loop {
+            let best = (0..n)
+                .map(|b| 1 << b)
+                .filter(|bit| cube.mask & bit == 0)
+                .map(|bit| (bit, Implicant { value: cube.value & !bit, mask: cube.mask | bit }))
+                .filter(|(_, raised)| !off.iter().any(|r| intersects(r, raised)))
+                .max_by_key(|(bit, raised)| {
+                    let swallowed = pending.iter().filter(|c| contains(raised, c)).count();
+                    let against = pending.iter()
+                        .filter(|c| c.mask & bit == 0 && (c.value ^ cube.value) & bit != 0)
+                        .count();
+                    (swallowed, against)
+                });
+            match best {
+                Some((_, raised)) => cube = raised,
+                None => break,
+            }
+        }
+
+        pending.retain(|c| !contains(&cube, c));
+        result.retain(|c| !contains(&cube, c));
+        result.push(cube);
+    }
+
+    result
+}
+
+// Remove cubes covered by the rest of the cover together with the don't-cares,
+// trying the smallest cubes first
+fn irredundant(cover: Vec<Implicant>, dont_cares: &[Implicant], n: usize) -> Vec<Implicant> {
+    let mut cover = cover;
+    cover.sort_by_key(|c| c.mask.count_ones());
+
+    let mut i = 0;
+    while i < cover.len() {
+        let cube = cover[i];
+        let mut rest: Vec<Implicant> = cover.iter().enumerate().filter(|&(j, _)| j != i).map(|(_, c)| *c).collect();
+        rest.extend_from_slice(dont_cares);
+        if tautology(&cofactor(&rest, &cube, n), n) {
+            cover.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    cover
+}
+
+fn last_gasp(cover: &[Implicant], dont_cares: &[Implicant], off: &[Implicant], n: usize) -> Vec<Implicant> {
+    let reduced: Vec<Implicant> = (0..cover.len())
+        .filter_map(|i| {
+            let mut rest: Vec<Implicant> = cover.iter().enumerate().filter(|&(j, _)| j != i).map(|(_, c)| *c).collect();
+            rest.extend_from_slice(dont_cares);
+            let uncovered = complement(&cofactor(&rest, &cover[i], n), n);
+            (!uncovered.is_empty()).then(|| intersection(&cover[i], &supercube(&uncovered, n)))
+        })
+        .collect();
+
+    let mut candidate = cover.to_vec();
+    for cube in expand(reduced.clone(), off, n) {
+        if !cover.contains(&cube) && reduced.iter().filter(|r| contains(&cube, r)).count() > 1 {
+            candidate.push(cube);
+        }
+    }
+    irredundant(candidate, dont_cares, n)
+}
+
+// Shrink each cube, biggest first, to the smallest cube holding the minterms
+// that no other cube or don't-care covers
+fn reduce(cover: &[Implicant], dont_cares: &[Implicant], n: usize) -> Vec<Implicant> {
+    let mut cover = cover.to_vec();
+    cover.sort_by_key(|c| std::cmp::Reverse(c.mask.count_ones()));
+
+    let mut i = 0;
+    while i < cover.len() {
+        let cube = cover[i];
+        let mut rest: Vec<Implicant> = cover.iter().enumerate().filter(|&(j, _)| j != i).map(|(_, c)| *c).collect();
+        rest.extend_from_slice(dont_cares);
+
+        let uncovered = complement(&cofactor(&rest, &cube, n), n);
+        if uncovered.is_empty() {
+            cover.remove(i);
+            continue;
+        }
+        cover[i] = intersection(&cube, &supercube(&uncovered, n));
+        i += 1;
+    }
+    cover
+}
+
+// A product term of a multi-output cover and the outputs it feeds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedTerm {
+    pub cube: Implicant,
+    pub outputs: Vec<usize>,
+}
+
+// Heuristic multi-output minimisation: one set of product terms for all the
+// outputs, sharing terms between outputs wherever that saves one. Candidates
+// are each output's own cover plus covers of the products of pairs of
+// outputs, where shared terms come from. Every candidate is connected to each
+// output it is an implicant of, then terms and connections the rest already
+// cover are dropped, least shared first
+pub fn espresso_multi(n: usize, on: &[Vec<Implicant>], dont_cares: &[Vec<Implicant>]) -> Vec<SharedTerm> {
+    let m = on.len();
+    let off: Vec<Vec<Implicant>> = (0..m)
+        .map(|o| {
+            let mut care = on[o].clone();
+            care.extend_from_slice(&dont_cares[o]);
+            complement(&care, n)
+        })
+        .collect();
+
+    let mut candidates: Vec<Implicant> = Vec::new();
+    for o in 0..m {
+        candidates.extend(espresso(n, &on[o], &dont_cares[o]));
+    }
+    for a in 0..m {
+        for b in a + 1..m {
+            let both = product(&on[a], &on[b]);
+            let mut free = product(&on[a], &dont_cares[b]);
+            free.extend(product(&dont_cares[a], &on[b]));
+            free.extend(product(&dont_cares[a], &dont_cares[b]));
+            candidates.extend(espresso(n, &both, &free));
+        }
+    }
+    candidates.sort();
+    candidates.dedup();
+
+    let implicant_of = |cube: &Implicant, o: usize| !off[o].iter().any(|r| intersects(r, cube));
+    let mut terms: Vec<SharedTerm> = candidates.into_iter()
+        .map(|cube| SharedTerm { cube, outputs: (0..m).filter(|&o| implicant_of(&cube, o)).collect() })
+        .filter(|term| !term.outputs.is_empty())
+        .collect();
+
+    terms = irredundant_multi(terms, dont_cares, n);
+
+    // Grow what is left as far as all of its outputs allow, merging terms
+    // that become the same
+    let mut grown: Vec<SharedTerm> = Vec::new();
+    for term in terms {
+        let mut cube = term.cube;
+        for bit in (0..n).map(|b| 1 << b) {
+            let raised = Implicant { value: cube.value & !bit, mask: cube.mask | bit };
+            if cube.mask & bit == 0 && term.outputs.iter().all(|&o| implicant_of(&raised, o)) {
+                cube = raised;
+            }
+        }
+        match grown.iter_mut().find(|t| t.cube == cube) {
+            Some(existing) => {
+                existing.outputs.extend(&term.outputs);
+                existing.outputs.sort();
+                existing.outputs.dedup();
+            }
+            None => grown.push(SharedTerm { cube, outputs: term.outputs }),
+        }
+    }
+
+    let mut terms = irredundant_multi(grown, dont_cares, n);
+    terms.sort_by(|a, b| (b.outputs.len(), a.cube).cmp(&(a.outputs.len(), b.cube)));
+    terms
+}
+
+// Is the part of output `o` under term `skip` covered by the other terms
+// feeding `o` and its don't-cares?
+fn covered_elsewhere(terms: &[SharedTerm], skip: usize, o: usize, dont_cares: &[Implicant], n: usize) -> bool {
+    let mut rest: Vec<Implicant> = terms.iter().enumerate()
+        .filter(|&(j, term)| j != skip && term.outputs.contains(&o))
+        .map(|(_, term)| term.cube)
+        .collect();
+    rest.extend_from_slice(dont_cares);
+    tautology(&cofactor(&rest, &terms[skip].cube, n), n)
+}
+
+fn irredundant_multi(mut terms: Vec<SharedTerm>, dont_cares: &[Vec<Implicant>], n: usize) -> Vec<SharedTerm> {
+    terms.sort_by_key(|term| (term.outputs.len(), std::cmp::Reverse(term.cube.literals(n))));
+
+    // Whole terms first, since each one saves a row of the AND plane
+    let mut i = 0;
+    while i < terms.len() {
+        if terms[i].outputs.iter().all(|&o| covered_elsewhere(&terms, i, o, &dont_cares[o], n)) {
+            terms.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    // Then single connections of the OR plane
+    for i in 0..terms.len() {
+        let outputs = terms[i].outputs.clone();
+        for o in outputs {
+            if covered_elsewhere(&terms, i, o, &dont_cares[o], n) {
+                terms[i].outputs.retain(|&x| x != o);
+            }
+        }
+    }
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::random;
+    use crate::eval::eval;
+    use crate::parser::{parse_system, Dialect};
+    use std::collections::HashMap;
+
+    // Up to `max` random cubes of `n` inputs
+    fn random_cover(seed: &mut u64, n: usize, max: usize) -> Vec<Implicant> {
+        (0..random(seed, max + 1))
+            .map(|_| {
+                let mut cube = Implicant { value: 0, mask: 0 };
+                for bit in (0..n).map(|b| 1 << b) {
+                    match random(seed, 3) {
+                        0 => cube.mask |= bit,
+                        1 => cube.value |= bit,
+                        _ => {}
+                    }
+                }
+                cube
+            })
+            .collect()
+    }
+
+    fn covered(cover: &[Implicant], row: usize) -> bool {
+        cover.iter().any(|cube| cube.covers(row))
+    }
+
+    #[test]
+    fn espresso_covers_the_on_set_and_misses_the_off_set() {
+        let mut seed = 0xbf58476d1ce4e5b9;
+        for _ in 0..300 {
+            let n = 1 + random(&mut seed, 7);
+            let on = random_cover(&mut seed, n, 8);
+            let dont_cares = random_cover(&mut seed, n, 3);
+            let result = espresso(n, &on, &dont_cares);
+            // Rows in both sets are don't-cares
+            for row in (0..1 << n).filter(|&row| !covered(&dont_cares, row)) {
+                assert_eq!(covered(&result, row), covered(&on, row), "{:?} {:?}", on, dont_cares);
+            }
+        }
+    }
+
+    #[test]
+    fn shared_terms_cover_each_output() {
+        let mut seed = 0x94d049bb133111eb;
+        for _ in 0..100 {
+            let n = 1 + random(&mut seed, 6);
+            let m = 1 + random(&mut seed, 3);
+            let on: Vec<Vec<Implicant>> = (0..m).map(|_| random_cover(&mut seed, n, 6)).collect();
+            let dont_cares: Vec<Vec<Implicant>> = (0..m).map(|_| random_cover(&mut seed, n, 2)).collect();
+            let terms = espresso_multi(n, &on, &dont_cares);
+            for o in 0..m {
+                let cover: Vec<Implicant> = terms.iter().filter(|t| t.outputs.contains(&o)).map(|t| t.cube).collect();
+                for row in (0..1 << n).filter(|&row| !covered(&dont_cares[o], row)) {
+                    assert_eq!(covered(&cover, row), covered(&on[o], row), "{:?} {:?}", on, dont_cares);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn complement_and_tautology_match_brute_force() {
+        let mut seed = 0x2545f4914f6cdd1d;
+        for _ in 0..300 {
+            let n = 1 + random(&mut seed, 7);
+            let cover = random_cover(&mut seed, n, 10);
+            let inverse = complement(&cover, n);
+            for row in 0..1 << n {
+                assert_ne!(covered(&cover, row), covered(&inverse, row), "{:?}", cover);
+            }
+            let everywhere = (0..1 << n).all(|row| covered(&cover, row));
+            assert_eq!(tautology(&cover, n), everywhere, "{:?}", cover);
+        }
+    }
+
+    #[test]
+    fn expression_covers_match_evaluation() {
+        let sources = [
+            "F = A & B | !C",
+            "F = (A ^ B) & (C !| D) | (A <-> D)",
+            "F = A -> B -> C",
+            "F = A !& (B !^ C)",
+            "F = A & !A | 0",
+        ];
+        for source in sources {
+            let system = parse_system(source, Dialect::Standard).unwrap();
+            let expr = &system.outputs[0].expr;
+            let vars: Vec<String> = ["A", "B", "C", "D"].map(String::from).to_vec();
+            let cover = expr_cover(expr, &vars).unwrap();
+            for row in 0..1usize << vars.len() {
+                // Bit n-1 is the first variable
+                let map: HashMap<String, bool> = vars.iter().enumerate()
+                    .map(|(i, var)| (var.clone(), row >> (vars.len() - 1 - i) & 1 == 1))
+                    .collect();
+                assert_eq!(covered(&cover, row), eval(expr, &map), "{}", source);
+            }
+        }
+    }
+}
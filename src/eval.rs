@@ -1,9 +1,29 @@
-use crate::expr::Expr;
+use crate::expr::{Expr, Output, System};
 use std::collections::HashMap;
+use std::fmt;
+
+// The value of an output on one row: 0, 1, or X when the row is a don't-care
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Logic {
+    Zero,
+    One,
+    DontCare,
+}
+
+impl fmt::Display for Logic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Logic::Zero => write!(f, "0"),
+            Logic::One => write!(f, "1"),
+            Logic::DontCare => write!(f, "X"),
+        }
+    }
+}
 
 pub fn eval(expr: &Expr, vars : &HashMap<String, bool>) -> bool {
     match expr {
-        Expr::Var(name) => *vars.get(name).unwrap_or(&false),
+        Expr::Var(name) | Expr::Ref(name) => *vars.get(name).unwrap_or(&false),
+        Expr::Const(value) => *value,
         Expr::Not(inner) => !eval(inner, vars),
         Expr::And(a, b) => eval(a, vars) && eval(b, vars),
         Expr::Or(a, b) => eval(a, vars) || eval(b, vars),
@@ -11,5 +31,27 @@ pub fn eval(expr: &Expr, vars : &HashMap<String, bool>) -> bool {
         Expr::Xnor(a, b) => !(eval(a, vars) ^ eval(b, vars)),
         Expr::Nand(a, b) => !(eval(a, vars) && eval(b, vars)),
         Expr::Nor(a, b) => !(eval(a, vars) || eval(b, vars)),
+        Expr::Implies(a, b) => !eval(a, vars) || eval(b, vars),
+        Expr::Iff(a, b) => eval(a, vars) == eval(b, vars),
+    }
+}
+
+// Evaluate the wires of a system in order, adding each value to `vars` so
+// later wires and the outputs can read it through `Expr::Ref`
+pub fn eval_wires(system: &System, vars: &mut HashMap<String, bool>) {
+    for definition in &system.definitions {
+        let value = eval(&definition.expr, vars);
+        vars.insert(definition.name.clone(), value);
+    }
+}
+
+// Evaluate an output, giving X wherever its don't-care condition holds
+pub fn eval_output(output: &Output, vars: &HashMap<String, bool>) -> Logic {
+    if output.dont_care.as_ref().is_some_and(|dc| eval(dc, vars)) {
+        Logic::DontCare
+    } else if eval(&output.expr, vars) {
+        Logic::One
+    } else {
+        Logic::Zero
     }
 }
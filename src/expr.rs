@@ -1,6 +1,11 @@
+use std::fmt;
+use std::ops::Range;
+
 #[derive(Debug, Clone)]
 pub enum Expr {
     Var(String),
+    Const(bool),
+    Ref(String),  // Reads the wire of a `let` definition
     Not(Box<Expr>),
     And(Box<Expr>, Box<Expr>),
     Or(Box<Expr>, Box<Expr>),
@@ -8,4 +13,177 @@ pub enum Expr {
     Xor(Box<Expr>, Box<Expr>),
     Nand(Box<Expr>, Box<Expr>),
     Nor(Box<Expr>, Box<Expr>),
+    Implies(Box<Expr>, Box<Expr>),
+    Iff(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    // The product term that is true only on row `index`; the first variable is the MSB
+    pub fn minterm(vars: &[String], index: usize) -> Expr {
+        let literals = vars.iter().enumerate().map(|(i, v)| {
+            let bit = (index >> (vars.len() - 1 - i)) & 1 == 1;
+            if bit { Expr::Var(v.clone()) } else { Expr::Not(Box::new(Expr::Var(v.clone()))) }
+        });
+        Expr::all(literals)
+    }
+
+    // The sum term that is false only on row `index`
+    pub fn maxterm(vars: &[String], index: usize) -> Expr {
+        let literals = vars.iter().enumerate().map(|(i, v)| {
+            let bit = (index >> (vars.len() - 1 - i)) & 1 == 1;
+            if bit { Expr::Not(Box::new(Expr::Var(v.clone()))) } else { Expr::Var(v.clone()) }
+        });
+        Expr::any(literals)
+    }
+
+    // AND of all terms, 1 if there are none
+    pub fn all(terms: impl IntoIterator<Item = Expr>) -> Expr {
+        terms.into_iter()
+            .reduce(|a, b| Expr::And(Box::new(a), Box::new(b)))
+            .unwrap_or(Expr::Const(true))
+    }
+
+    // OR of all terms, 0 if there are none
+    pub fn any(terms: impl IntoIterator<Item = Expr>) -> Expr {
+        terms.into_iter()
+            .reduce(|a, b| Expr::Or(Box::new(a), Box::new(b)))
+            .unwrap_or(Expr::Const(false))
+    }
+}
+
+impl Expr {
+    // Binding strength, following the grammar: IFF binds loosest, atoms tightest
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Iff(..) => 1,
+            Expr::Implies(..) => 2,
+            Expr::Or(..) => 3,
+            Expr::Nor(..) => 4,
+            Expr::Xor(..) => 5,
+            Expr::Xnor(..) => 6,
+            Expr::And(..) => 7,
+            Expr::Nand(..) => 8,
+            Expr::Not(_) => 9,
+            Expr::Var(_) | Expr::Const(_) | Expr::Ref(_) => 10,
+        }
+    }
+}
+
+impl Expr {
+    // The printed form, with the byte range of the subterm at each of `paths`.
+    // A path picks the first (0) or second (1) operand at each step down from
+    // the root; the range of a bracketed subterm takes in its brackets
+    pub fn marked(&self, paths: &[Vec<usize>]) -> (String, Vec<Range<usize>>) {
+        let mut out = String::new();
+        let mut ranges = Vec::new();
+        self.write(&mut out, false, &mut Vec::new(), paths, &mut ranges);
+        ranges.sort_by_key(|range| range.start);
+        (out, ranges)
+    }
+
+    fn write(&self, out: &mut String, wrap: bool, path: &mut Vec<usize>, paths: &[Vec<usize>], ranges: &mut Vec<Range<usize>>) {
+        let start = out.len();
+        if wrap {
+            out.push('(');
+        }
+        let mut operand = |expr: &Expr, i: usize, wrap: bool, out: &mut String| {
+            path.push(i);
+            expr.write(out, wrap, path, paths, ranges);
+            path.pop();
+        };
+        let (a, op, b) = match self {
+            Expr::Var(name) | Expr::Ref(name) => (None, name.as_str(), None),
+            Expr::Const(false) => (None, "0", None),
+            Expr::Const(true) => (None, "1", None),
+            Expr::Not(inner) => {
+                out.push('!');
+                operand(inner, 0, inner.precedence() < self.precedence(), out);
+                (None, "", None)
+            }
+            Expr::And(a, b) => (Some(a), " & ", Some(b)),
+            Expr::Or(a, b) => (Some(a), " | ", Some(b)),
+            Expr::Xor(a, b) => (Some(a), " ^ ", Some(b)),
+            Expr::Xnor(a, b) => (Some(a), " !^ ", Some(b)),
+            Expr::Nand(a, b) => (Some(a), " !& ", Some(b)),
+            Expr::Nor(a, b) => (Some(a), " !| ", Some(b)),
+            Expr::Implies(a, b) => (Some(a), " -> ", Some(b)),
+            Expr::Iff(a, b) => (Some(a), " <-> ", Some(b)),
+        };
+
+        if let (Some(a), Some(b)) = (a, b) {
+            // Everything is left associative except implication
+            let right_assoc = matches!(self, Expr::Implies(..));
+            let wrap_left = a.precedence() < self.precedence()
+                || (right_assoc && a.precedence() == self.precedence());
+            let wrap_right = b.precedence() < self.precedence()
+                || (!right_assoc && b.precedence() == self.precedence());
+            operand(a, 0, wrap_left, out);
+            out.push_str(op);
+            operand(b, 1, wrap_right, out);
+        } else {
+            out.push_str(op);
+        }
+
+        if wrap {
+            out.push(')');
+        }
+        if paths.contains(path) {
+            ranges.push(start..out.len());
+        }
+    }
+}
+
+// Prints in the standard dialect with only the parentheses the grammar needs,
+// so the output can be parsed back into the same tree
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.marked(&[]).0)
+    }
+}
+
+// An intermediate wire, e.g. `let t = A & B`
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub expr: Expr,
+}
+
+// One named output, e.g. `S = A ^ B`
+#[derive(Debug, Clone)]
+pub struct Output {
+    pub name: String,
+    pub expr: Expr,
+    pub dont_care: Option<Expr>,  // true on the rows whose value does not matter
+}
+
+// Everything entered on the input screen. A plain expression is a system
+// with a single output called OUT
+#[derive(Debug, Clone)]
+pub struct System {
+    pub inputs: Vec<String>,           // variable order given by `F(A,B,C) = ...`, if any
+    pub definitions: Vec<Definition>,  // in order, each may use the ones before it
+    pub outputs: Vec<Output>,
+}
+
+impl System {
+    // Replace every wire reference with the expression that drives it
+    pub fn inline(&self, expr: &Expr) -> Expr {
+        let inline = |e: &Expr| Box::new(self.inline(e));
+        match expr {
+            Expr::Ref(name) => {
+                let definition = self.definitions.iter().find(|d| &d.name == name).unwrap();
+                self.inline(&definition.expr)
+            }
+            Expr::Var(_) | Expr::Const(_) => expr.clone(),
+            Expr::Not(inner) => Expr::Not(inline(inner)),
+            Expr::And(a, b) => Expr::And(inline(a), inline(b)),
+            Expr::Or(a, b) => Expr::Or(inline(a), inline(b)),
+            Expr::Xor(a, b) => Expr::Xor(inline(a), inline(b)),
+            Expr::Xnor(a, b) => Expr::Xnor(inline(a), inline(b)),
+            Expr::Nand(a, b) => Expr::Nand(inline(a), inline(b)),
+            Expr::Nor(a, b) => Expr::Nor(inline(a), inline(b)),
+            Expr::Implies(a, b) => Expr::Implies(inline(a), inline(b)),
+            Expr::Iff(a, b) => Expr::Iff(inline(a), inline(b)),
+        }
+    }
 }
@@ -1,14 +1,25 @@
-use crate::expr::Expr;
-use crate::truth_table::variables;
-use crate::eval::eval;
-use std::collections::HashMap;
-use comfy_table::{
-    Table, Row, presets::UTF8_FULL,
-    modifiers::UTF8_ROUND_CORNERS,
-    ContentArrangement
+use crate::eval::Logic;
+use crate::minimize::{minimize_sop, on_and_dont_care_rows, Implicant, MAX_EXACT_VARIABLES};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    symbols,
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, canvas::{Canvas, Context}},
+    Frame,
 };
 
-// Generate Gray code sequence of n-bits 
+// Size of one K-Map cell on screen
+const CELL_WIDTH: u16 = 8;
+const CELL_HEIGHT: u16 = 3;
+
+// Colours the groups cycle through
+const GROUP_COLORS: [Color; 8] = [
+    Color::Red, Color::Green, Color::Blue, Color::Magenta,
+    Color::Cyan, Color::LightRed, Color::LightGreen, Color::LightBlue,
+];
+
+// Generate Gray code sequence of n-bits
 fn gray_code(n: usize) -> Vec<Vec<bool>> {
     if n == 0 {
         return vec![vec![]];
@@ -17,7 +28,7 @@ fn gray_code(n: usize) -> Vec<Vec<bool>> {
     let prev = gray_code(n - 1);
     let mut res = Vec::new();
 
-    // Prefix with 0 
+    // Prefix with 0
     for code in &prev {
         let mut new = vec![false];
         new.extend(code.clone());
@@ -33,55 +44,463 @@ fn gray_code(n: usize) -> Vec<Vec<bool>> {
     res
 }
 
-// Generate K-Map for any expression for 2-6 variables
-pub fn k_map(expr: &Expr) -> String {
-    let vars = variables(expr);
+fn bits_label(bits: &[bool]) -> String {
+    bits.iter().map(|&b| if b { '1' } else { '0' }).collect()
+}
+
+// A prime implicant drawn as a loop on the map
+struct Group {
+    implicant: Implicant,
+    term: String,
+    essential: bool,
+    in_cover: bool,   // part of the minimal SOP
+}
+
+// How the variables are spread over the map. In order of significance: the
+// variables that pick a sub-map, the row variables, the column variables and
+// the map-entered variables folded into the cells
+struct MapShape {
+    sub_vars: usize,
+    row_vars: usize,
+    col_vars: usize,
+    entered: usize,
+}
+
+// K-Map of one output with its prime implicants drawn as coloured groups.
+// Past 6 mapped variables it becomes a set of 4x4 sub-maps, one for each
+// combination of the leading variables. `selected` picks out one group; None
+// shows them all alike
+pub struct KMapView {
+    values: Vec<Logic>,
+    vars: Vec<String>,
+    groups: Vec<Group>,
+    selected: Option<usize>,
+    row_vars: Option<usize>,  // chosen number of row variables, None for half
+    entered: usize,           // variables entered into the cells (VEM), 0 to 2
+    first_submap: usize,      // sub-map shown first, for scrolling
+}
+
+impl KMapView {
+    // `values` is the output's value on every row (see `truth_table::output_values`)
+    pub fn new(values: Vec<Logic>, vars: Vec<String>) -> Self {
+        let mut view = Self {
+            values,
+            vars,
+            groups: Vec::new(),
+            selected: None,
+            row_vars: None,
+            entered: 0,
+            first_submap: 0,
+        };
+
+        if view.is_supported() {
+            let (on, dont_cares) = on_and_dont_care_rows(&view.values);
+            let result = minimize_sop(view.vars.len(), &on, &dont_cares);
+            view.groups = result.primes.iter()
+                .map(|&implicant| Group {
+                    implicant,
+                    term: implicant.to_expr(&view.vars).to_string(),
+                    essential: result.essential.contains(&implicant),
+                    in_cover: result.cover.contains(&implicant),
+                })
+                .collect();
+        }
+
+        view
+    }
+
+    fn is_supported(&self) -> bool {
+        (1..=MAX_EXACT_VARIABLES).contains(&self.vars.len())
+    }
+
+    fn shape(&self) -> MapShape {
+        let entered = self.entered.min(self.vars.len() - 1);
+        let mapped = self.vars.len() - entered;
+        let (sub_vars, map_vars) = if mapped > 6 { (mapped - 4, 4) } else { (0, mapped) };
+        let row_vars = self.row_vars.unwrap_or(map_vars / 2).min(map_vars);
+        MapShape { sub_vars, row_vars, col_vars: map_vars - row_vars, entered }
+    }
+
+    // Groups are only drawn on a plain map, not with map-entered variables
+    fn shows_groups(&self) -> bool {
+        self.is_supported() && self.shape().entered == 0
+    }
+
+    // Step through the groups; past either end nothing is selected
+    pub fn select_next(&mut self) {
+        if !self.shows_groups() {
+            return;
+        }
+        self.selected = match self.selected {
+            None if !self.groups.is_empty() => Some(0),
+            Some(i) if i + 1 < self.groups.len() => Some(i + 1),
+            _ => None,
+        };
+    }
+
+    pub fn select_prev(&mut self) {
+        if !self.shows_groups() {
+            return;
+        }
+        self.selected = match self.selected {
+            None => self.groups.len().checked_sub(1),
+            Some(i) => i.checked_sub(1),
+        };
+    }
 
-    if vars.len() < 2 || vars.len() > 6 {
-        return "K-Map supported only for 2 to 6 variables".to_string();
+    // Next row/column split, from all columns to all rows
+    pub fn cycle_split(&mut self) {
+        if !self.is_supported() {
+            return;
+        }
+        let shape = self.shape();
+        let map_vars = shape.row_vars + shape.col_vars;
+        self.row_vars = Some((shape.row_vars + 1) % (map_vars + 1));
     }
 
-    // Split into row and column variables
-    let row_vars = vars[..vars.len() / 2].to_vec();
-    let col_vars = vars[vars.len() / 2..].to_vec();
+    // Fold 0, 1 or 2 of the last variables into the cells
+    pub fn cycle_entered(&mut self) {
+        if !self.is_supported() {
+            return;
+        }
+        self.entered = (self.entered + 1) % 3;
+        self.row_vars = None;
+        self.first_submap = 0;
+        if !self.shows_groups() {
+            self.selected = None;
+        }
+    }
 
-    let row_gray = gray_code(row_vars.len());
-    let col_gray = gray_code(col_vars.len());
+    // With at most MAX_EXACT_VARIABLES inputs there are few enough submaps
+    // to count
+    pub fn scroll_submaps(&mut self, forward: bool) {
+        if !self.is_supported() {
+            return;
+        }
+        let count = 1usize << self.shape().sub_vars;
+        self.first_submap = if forward {
+            (self.first_submap + 1).min(count - 1)
+        } else {
+            self.first_submap.saturating_sub(1)
+        };
+    }
 
-    // Prepare table
-    let mut table = Table::new();
-    table
-        .load_preset(UTF8_FULL)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_content_arrangement(ContentArrangement::Dynamic);
+    // Minterm number of a cell. `sub`, `row` and `col` are positions in Gray
+    // code order, `entered` is the value of the map-entered variables
+    fn cell_index(&self, shape: &MapShape, sub: usize, row: usize, col: usize, entered: usize) -> usize {
+        let gray = |i: usize| i ^ (i >> 1);
+        let index = gray(sub);
+        let index = index << shape.row_vars | gray(row);
+        let index = index << shape.col_vars | gray(col);
+        index << shape.entered | entered
+    }
 
-    // ✅ FIXED: Proper header row
-    let mut header_cells = vec![format!("{}\\{}", row_vars.join(""), col_vars.join(""))];
-    for bits in &col_gray {
-        let cell = bits.iter().map(|&b| if b { "1" } else { "0" }).collect::<String>();
-        header_cells.push(cell);
+    fn group_color(index: usize) -> Color {
+        GROUP_COLORS[index % GROUP_COLORS.len()]
     }
-    table.set_header(Row::from(header_cells));
 
-    // Rows
-    for row in &row_gray {
-        let mut row_cells = vec![row.iter().map(|&b| if b { "1" } else { "0" }).collect::<String>()];
+    pub fn render(&self, f: &mut Frame, area: Rect, title: &str) {
+        let block = Block::default().borders(Borders::ALL).title(title.to_string());
+        let inner = block.inner(area);
+        f.render_widget(block, area);
 
-        for col in &col_gray {
-            let mut assignment = HashMap::new();
+        if !self.is_supported() {
+            let message = format!("K-Map supported only for 1 to {} variables", MAX_EXACT_VARIABLES);
+            f.render_widget(Paragraph::new(message), inner);
+            return;
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(36)])
+            .split(chunks[0]);
+
+        // Maps keep a fixed cell size. One extra row and column hold the Gray
+        // code labels, and sub-maps get a line for their name above them
+        let shape = self.shape();
+        let rows = 1 << shape.row_vars;
+        let cols = 1 << shape.col_vars;
+        let submaps = 1usize << shape.sub_vars;
+        let label_height = u16::from(shape.sub_vars > 0);
+        let width = (cols as u16 + 1) * CELL_WIDTH;
+        let height = (rows as u16 + 1) * CELL_HEIGHT + label_height;
 
-            for (v, &val) in row_vars.iter().zip(row.iter()) {
-                assignment.insert(v.clone(), val);
+        // Tile as many sub-maps as fit, centred in the area
+        let space = columns[0];
+        let across = (space.width / (width + 1)).max(1) as usize;
+        let down = (space.height / height).max(1) as usize;
+        let shown = (submaps - self.first_submap).min(across * down);
+        let used_across = shown.min(across) as u16;
+        let used_down = shown.div_ceil(across) as u16;
+        let left = space.x + space.width.saturating_sub(used_across * (width + 1)) / 2;
+        let top = space.y + space.height.saturating_sub(used_down * height) / 2;
+
+        for (i, sub) in (self.first_submap..self.first_submap + shown).enumerate() {
+            let x = left + (i % across) as u16 * (width + 1);
+            let y = top + (i / across) as u16 * height;
+            let tile = Rect { x, y, width, height }.intersection(space);
+            if tile.is_empty() {
+                continue;
             }
-            for (v, &val) in col_vars.iter().zip(col.iter()) {
-                assignment.insert(v.clone(), val);
+
+            if shape.sub_vars > 0 {
+                let names = self.vars[..shape.sub_vars].concat();
+                let code = (0..shape.sub_vars).rev().map(|b| if (sub ^ (sub >> 1)) >> b & 1 == 1 { '1' } else { '0' }).collect::<String>();
+                let label = format!("{} = {}", names, code);
+                f.render_widget(Paragraph::new(Line::styled(label, Color::Cyan)).alignment(ratatui::layout::Alignment::Center), Rect { height: 1, ..tile });
             }
 
-            let result = eval(expr, &assignment);
-            row_cells.push(if result { "1".to_string() } else { "0".to_string() });
+            let map_area = Rect { y: tile.y + label_height, height: tile.height.saturating_sub(label_height), ..tile };
+            let canvas = Canvas::default()
+                .paint(|ctx| self.paint_map(ctx, &shape, sub))
+                .marker(symbols::Marker::Braille)
+                .x_bounds([-1.0, cols as f64])
+                .y_bounds([0.0, rows as f64 + 1.0]);
+            f.render_widget(canvas, map_area);
         }
-        table.add_row(Row::from(row_cells));
+
+        f.render_widget(self.legend(&shape, columns[1].height), columns[1]);
+
+        let status = match self.selected {
+            Some(i) => {
+                let group = &self.groups[i];
+                let kind = if group.essential { "essential" } else if group.in_cover { "in the minimal cover" } else { "redundant" };
+                Line::from(vec![
+                    Span::raw(format!("Group {}/{}: ", i + 1, self.groups.len())),
+                    Span::styled(group.term.clone(), Style::default().fg(Self::group_color(i)).add_modifier(Modifier::BOLD)),
+                    Span::raw(format!(" ({})", kind)),
+                ])
+            }
+            None => {
+                let mut hints = vec!["'s' split", "'v' map-entered variables"];
+                if self.shows_groups() {
+                    hints.insert(0, "Up/Down groups");
+                }
+                if shown < submaps {
+                    hints.push("PgUp/PgDn sub-maps");
+                }
+                let showing = if submaps > 1 {
+                    format!("sub-maps {}-{} of {}, ", self.first_submap + 1, self.first_submap + shown, submaps)
+                } else {
+                    String::new()
+                };
+                Line::from(format!("{}{}", showing, hints.join(", ")))
+            }
+        };
+        f.render_widget(Paragraph::new(status), chunks[1]);
     }
 
-    table.to_string()
+    // Grid, labels, cell values and group loops of one (sub-)map. A cell is
+    // one unit square; row 0 of the map is at the top
+    fn paint_map(&self, ctx: &mut Context, shape: &MapShape, sub: usize) {
+        let rows = 1 << shape.row_vars;
+        let cols = 1 << shape.col_vars;
+        let top = rows as f64;
+        let char_width = 1.0 / CELL_WIDTH as f64;
+
+        // Grid lines
+        for r in 0..=rows {
+            let y = top - r as f64;
+            ctx.draw(&ratatui::widgets::canvas::Line { x1: 0.0, y1: y, x2: cols as f64, y2: y, color: Color::DarkGray });
+        }
+        for c in 0..=cols {
+            let x = c as f64;
+            ctx.draw(&ratatui::widgets::canvas::Line { x1: x, y1: 0.0, x2: x, y2: top, color: Color::DarkGray });
+        }
+
+        // Groups, with the selected one drawn last so it ends up on top
+        if self.shows_groups() {
+            let order = (0..self.groups.len())
+                .filter(|&i| Some(i) != self.selected)
+                .chain(self.selected);
+            for i in order {
+                let color = match self.selected {
+                    Some(s) if s != i => Color::DarkGray,
+                    _ => Self::group_color(i),
+                };
+                self.draw_group(ctx, shape, sub, &self.groups[i].implicant, i, color);
+            }
+        }
+
+        ctx.layer();
+
+        // Variable names in the corner, Gray code labels along the edges
+        let first = shape.sub_vars;
+        let row_names = self.vars[first..first + shape.row_vars].concat();
+        let col_names = self.vars[first + shape.row_vars..first + shape.row_vars + shape.col_vars].concat();
+        let corner = format!("{}\\{}", row_names, col_names);
+        ctx.print(-1.0 + char_width, top + 0.5, Line::styled(corner, Color::Cyan));
+        for (c, bits) in gray_code(shape.col_vars).iter().enumerate() {
+            let label = bits_label(bits);
+            let x = c as f64 + 0.5 - label.len() as f64 * char_width / 2.0;
+            ctx.print(x, top + 0.5, Line::styled(label, Color::Cyan));
+        }
+        for (r, bits) in gray_code(shape.row_vars).iter().enumerate() {
+            let label = bits_label(bits);
+            let x = -0.5 - label.len() as f64 * char_width / 2.0;
+            ctx.print(x, top - r as f64 - 0.5, Line::styled(label, Color::Cyan));
+        }
+
+        // Cell values, in the colour of the selected group where it covers them
+        let selected = self.selected.map(|i| (i, self.groups[i].implicant));
+        for r in 0..rows {
+            for c in 0..cols {
+                let (text, style) = if shape.entered > 0 {
+                    self.entered_cell(shape, sub, r, c)
+                } else {
+                    let index = self.cell_index(shape, sub, r, c, 0);
+                    let value = self.values[index];
+                    let style = match selected {
+                        Some((i, implicant)) if implicant.covers(index) => {
+                            Style::default().fg(Self::group_color(i)).add_modifier(Modifier::BOLD)
+                        }
+                        _ => Self::value_style(value),
+                    };
+                    (value.to_string(), style)
+                };
+                let x = c as f64 + 0.5 - text.chars().count() as f64 * char_width / 2.0;
+                ctx.print(x, top - r as f64 - 0.5, Line::styled(text, style));
+            }
+        }
+    }
+
+    fn value_style(value: Logic) -> Style {
+        match value {
+            Logic::One => Style::default().fg(Color::White),
+            Logic::Zero => Style::default().fg(Color::DarkGray),
+            Logic::DontCare => Style::default().fg(Color::Yellow),
+        }
+    }
+
+    // With map-entered variables a cell holds what is left of the function
+    // once the map variables are fixed: 0, 1, X or a minimal expression in
+    // the entered variables, written textbook style (`C'D + CD'`)
+    fn entered_cell(&self, shape: &MapShape, sub: usize, row: usize, col: usize) -> (String, Style) {
+        let residual: Vec<Logic> = (0..1 << shape.entered)
+            .map(|e| self.values[self.cell_index(shape, sub, row, col, e)])
+            .collect();
+
+        if let Some(&first) = residual.first().filter(|&&v| residual.iter().all(|&other| other == v)) {
+            return (first.to_string(), Self::value_style(first));
+        }
+
+        let (on, dont_cares) = on_and_dont_care_rows(&residual);
+        let names = &self.vars[self.vars.len() - shape.entered..];
+        let cover = minimize_sop(shape.entered, &on, &dont_cares).cover;
+        let text = match cover.as_slice() {
+            [] => "0".to_string(),
+            _ => cover.iter()
+                .map(|term| textbook_term(term, names))
+                .collect::<Vec<_>>()
+                .join("+"),
+        };
+        (text, Style::default().fg(Color::LightCyan))
+    }
+
+    // A group covers a set of map rows times a set of map columns. Each
+    // contiguous run of rows and columns gets its own loop; a side that
+    // continues round the edge of the map to the opposite side is left open
+    fn draw_group(&self, ctx: &mut Context, shape: &MapShape, sub: usize, implicant: &Implicant, index: usize, color: Color) {
+        let rows = 1 << shape.row_vars;
+        let cols = 1 << shape.col_vars;
+        let covers = |r: usize, c: usize| implicant.covers(self.cell_index(shape, sub, r, c, 0));
+        let in_rows: Vec<bool> = (0..rows).map(|r| (0..cols).any(|c| covers(r, c))).collect();
+        let in_cols: Vec<bool> = (0..cols).map(|c| (0..rows).any(|r| covers(r, c))).collect();
+
+        // Nest overlapping loops inside each other
+        let inset = 0.08 + 0.05 * (index % 4) as f64;
+        let top = rows as f64;
+
+        for (r0, r1, open_top, open_bottom) in runs(&in_rows) {
+            for (c0, c1, open_left, open_right) in runs(&in_cols) {
+                let x0 = c0 as f64 + inset;
+                let x1 = c1 as f64 + 1.0 - inset;
+                let y0 = top - r1 as f64 - 1.0 + inset;   // bottom edge
+                let y1 = top - r0 as f64 - inset;         // top edge
+
+                // Open sides run out to the edge of the map
+                let (left, right) = (if open_left { 0.0 } else { x0 }, if open_right { cols as f64 } else { x1 });
+                let (bottom, upper) = (if open_bottom { 0.0 } else { y0 }, if open_top { top } else { y1 });
+
+                let mut side = |x1: f64, y1: f64, x2: f64, y2: f64| {
+                    ctx.draw(&ratatui::widgets::canvas::Line { x1, y1, x2, y2, color });
+                };
+                if !open_top { side(left, y1, right, y1); }
+                if !open_bottom { side(left, y0, right, y0); }
+                if !open_left { side(x0, bottom, x0, upper); }
+                if !open_right { side(x1, bottom, x1, upper); }
+            }
+        }
+    }
+
+    fn legend(&self, shape: &MapShape, height: u16) -> Paragraph<'_> {
+        if shape.entered > 0 {
+            let names = self.vars[self.vars.len() - shape.entered..].join(", ");
+            let lines = vec![
+                Line::from(format!("Map-entered variables: {}", names)),
+                Line::from(""),
+                Line::from("Each cell holds the function of"),
+                Line::from("these variables for that cell."),
+                Line::from("Groups are shown without them."),
+            ];
+            return Paragraph::new(lines).block(Block::default().borders(Borders::LEFT));
+        }
+
+        let mut lines = vec![Line::from("Prime implicants (* essential)"), Line::from("")];
+        for (i, group) in self.groups.iter().enumerate() {
+            let marker = if Some(i) == self.selected { "> " } else { "  " };
+            let kind = if group.essential { "*" } else if group.in_cover { "" } else { " (redundant)" };
+            let mut style = Style::default().fg(Self::group_color(i));
+            if Some(i) == self.selected {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            lines.push(Line::from(vec![
+                Span::raw(marker),
+                Span::styled("■ ", style),
+                Span::styled(format!("{}{}", group.term, kind), style),
+            ]));
+        }
+
+        // Keep the selected group in view when the list is long
+        let offset = self.selected.map_or(0, |i| (i + 3).saturating_sub(height as usize));
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::LEFT))
+            .scroll((offset as u16, 0))
+    }
+}
+
+// Product term as juxtaposed literals with primes for complements, `AB'`
+fn textbook_term(term: &Implicant, names: &[String]) -> String {
+    let n = names.len();
+    let literals: String = names.iter().enumerate()
+        .filter(|&(i, _)| term.mask >> (n - 1 - i) & 1 == 0)
+        .map(|(i, name)| if term.value >> (n - 1 - i) & 1 == 1 { name.clone() } else { format!("{}'", name) })
+        .collect();
+    if literals.is_empty() { "1".to_string() } else { literals }
+}
+
+// Contiguous runs of set positions as (first, last, open at start, open at
+// end). A run touching one edge is open there when the set carries on from
+// the opposite edge
+fn runs(set: &[bool]) -> Vec<(usize, usize, bool, bool)> {
+    let len = set.len();
+    let wraps = set[0] && set[len - 1] && !set.iter().all(|&b| b);
+
+    let mut result = Vec::new();
+    let mut start = None;
+    for (i, &member) in set.iter().chain([&false]).enumerate() {
+        match (start, member) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                result.push((s, i - 1, wraps && s == 0, wraps && i == len));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    result
 }
@@ -1,13 +1,32 @@
 use ratatui::{
-    layout::Rect,
+    layout::{Position, Rect},
     style::Color,
     symbols,
+    text::Line,
     widgets::{Block, Borders, canvas::Canvas},
     Frame,
 };
-use crate::expr::Expr;
+use crate::dag::{Dag, Node};
+use crate::expr::{Expr, Output, System};
+use std::cell::Cell;
 use std::collections::HashMap;
 
+// Spacing of the layout, in canvas units
+const LAYER_SPACING: f64 = 70.0;
+const GATE_SPACING: f64 = 10.0;
+const WIRE_SPACING: f64 = 6.0;   // between a wire passing through a layer and its neighbours
+const DUMMY_WIDTH: f64 = 40.0;   // how far a passing wire runs straight
+
+// The canvas shows at least this width, and keeps this ratio of width to
+// height, so small circuits look as they always have
+const MIN_WIDTH: f64 = 300.0;
+const ASPECT: f64 = 2.5;
+
+// Passes of the crossing reduction and of the vertical placement. Placement
+// ends on a pass that lines gates up with their inputs, so it needs an odd count
+const ORDERING_SWEEPS: usize = 12;
+const PLACEMENT_SWEEPS: usize = 7;
+
 #[derive(Clone, Debug)]
 pub struct GateInstance {
     pub gate_type: GateType,
@@ -29,6 +48,8 @@ pub enum GateType {
     Xor,
     Xnor,
     Input(String), // Variable input
+    Const(bool),   // Input tied high (1) or low (0)
+    Output(String), // Named output terminal
 }
 
 pub struct LogicGatesViewer {
@@ -36,8 +57,15 @@ pub struct LogicGatesViewer {
     pub pan_y: f64,
     pub zoom: f64,
     gates: Vec<GateInstance>,
-    variables: Vec<String>,
-    expression: Option<Expr>,
+    dag: Dag,
+    wire_labels: Vec<(usize, String)>,  // gate driving each `let` wire, and its name
+    system: Option<System>,
+    pub simulating: bool,
+    input_values: HashMap<String, bool>,  // kept by name across circuits
+    values: Vec<bool>,                    // output of every gate
+    area: Cell<Rect>,                     // where it was last drawn, to place clicks
+    extent: [f64; 2],                     // width and height the layout needs
+    routes: HashMap<(usize, usize), Vec<(f64, f64)>>,  // bends of each wire, by driving and driven gate
 }
 
 impl LogicGatesViewer {
@@ -47,192 +75,309 @@ impl LogicGatesViewer {
             pan_y: 0.0,
             zoom: 1.0,
             gates: Vec::new(),
-            variables: Vec::new(),
-            expression: None,
+            dag: Dag::default(),
+            wire_labels: Vec::new(),
+            system: None,
+            simulating: false,
+            input_values: HashMap::new(),
+            values: Vec::new(),
+            area: Cell::new(Rect::default()),
+            extent: [MIN_WIDTH, MIN_WIDTH / ASPECT],
+            routes: HashMap::new(),
         }
     }
 
+    // A single expression, drawn as a system with one output called OUT
     pub fn set_expression(&mut self, expr: Expr) {
-        self.expression = Some(expr.clone());
-        self.generate_circuit_from_expression(&expr);
+        let output = Output { name: "OUT".to_string(), expr, dont_care: None };
+        self.set_system(System { inputs: Vec::new(), definitions: Vec::new(), outputs: vec![output] });
+    }
+
+    pub fn set_system(&mut self, system: System) {
+        self.generate_circuit_from_system(&system);
+        self.system = Some(system);
+        self.simulate();
+    }
+
+    // Works out every gate from the inputs, then copies each output's driver
+    // to its terminal
+    fn simulate(&mut self) {
+        let inputs: Vec<bool> = self.dag.inputs.iter()
+            .map(|name| self.input_values.get(name).copied().unwrap_or(false))
+            .collect();
+        self.values = self.dag.evaluate(&inputs);
+        for &(_, driver_id) in &self.dag.outputs {
+            self.values.push(self.values[driver_id]);
+        }
+    }
+
+    pub fn toggle_simulation(&mut self) {
+        self.simulating = !self.simulating;
+    }
+
+    // Flip the `index`th input, counting from 0 in the order the inputs are drawn
+    pub fn toggle_input(&mut self, index: usize) {
+        if let (true, Some(name)) = (self.simulating, self.dag.inputs.get(index)) {
+            let value = self.input_values.entry(name.clone()).or_insert(false);
+            *value = !*value;
+            self.simulate();
+        }
+    }
+
+    // Flip the input drawn under a mouse click at a terminal cell
+    pub fn click(&mut self, column: u16, row: u16) {
+        let area = self.area.get();
+        let inner = Rect::new(area.x + 1, area.y + 1, area.width.saturating_sub(2), area.height.saturating_sub(2));
+        if !self.simulating || !inner.contains(Position::new(column, row)) {
+            return;
+        }
+        let ([left, right], [bottom, top]) = self.bounds();
+        let cell_width = (right - left) / inner.width as f64;
+        let cell_height = (top - bottom) / inner.height as f64;
+        let x = left + ((column - inner.x) as f64 + 0.5) * cell_width;
+        let y = bottom + ((inner.bottom() - 1 - row) as f64 + 0.5) * cell_height;
+        let (x, y) = (x / self.zoom + self.pan_x, y / self.zoom + self.pan_y);
+
+        // Within a cell of the box counts, since a cell is coarse
+        let (slack_x, slack_y) = (cell_width / self.zoom, cell_height / self.zoom);
+        let hit = self.gates.iter().find_map(|gate| match &gate.gate_type {
+            GateType::Input(name)
+                if x >= gate.x - slack_x && x <= gate.x + gate.width + slack_x
+                    && y >= gate.y - slack_y && y <= gate.y + gate.height + slack_y => Some(name.clone()),
+            _ => None,
+        });
+        if let Some(index) = hit.and_then(|name| self.dag.inputs.iter().position(|v| *v == name)) {
+            self.toggle_input(index);
+        }
     }
 
-    fn generate_circuit_from_expression(&mut self, expr: &Expr) {
+    // The part of the plane the canvas shows, before panning and zooming
+    fn bounds(&self) -> ([f64; 2], [f64; 2]) {
+        ([0.0, self.extent[0]], [0.0, self.extent[1]])
+    }
+
+    // Colour of a wire or value at logic level `value`, green for every wire
+    // when not simulating
+    fn level_color(&self, value: bool) -> Color {
+        match (self.simulating, value) {
+            (false, _) => Color::Green,
+            (true, true) => Color::LightGreen,
+            (true, false) => Color::DarkGray,
+        }
+    }
+
+    // One gate per node of the circuit's DAG, so a subexpression used twice
+    // is drawn once with its output fanning out, then a terminal per output
+    fn generate_circuit_from_system(&mut self, system: &System) {
+        self.dag = Dag::from_system(system);
         self.gates.clear();
-        self.variables.clear();
-        
-        // Collect all variables
-        self.collect_variables(expr);
-        
-        // Create input gates for variables
-        let mut gate_id_map = HashMap::new();
-        for (i, var) in self.variables.iter().enumerate() {
-            let input_gate = GateInstance {
-                gate_type: GateType::Input(var.clone()),
-                x: 20.0,
-                y: 90.0 - (i as f64 * 25.0),
+        self.wire_labels = self.dag.wires.iter().map(|(name, id)| (*id, name.clone())).collect();
+
+        for node in &self.dag.nodes {
+            let (gate_type, width, height) = match *node {
+                Node::Input(i) => (GateType::Input(self.dag.inputs[i].clone()), 30.0, 15.0),
+                Node::Const(value) => (GateType::Const(value), 30.0, 15.0),
+                Node::Not(_) => (GateType::Not, 30.0, 15.0),
+                Node::And(..) => (GateType::And, 40.0, 20.0),
+                Node::Or(..) => (GateType::Or, 40.0, 20.0),
+                Node::Xor(..) => (GateType::Xor, 40.0, 20.0),
+                Node::Nand(..) => (GateType::Nand, 40.0, 20.0),
+                Node::Nor(..) => (GateType::Nor, 40.0, 20.0),
+                Node::Xnor(..) => (GateType::Xnor, 40.0, 20.0),
+            };
+            self.gates.push(GateInstance {
+                gate_type,
+                x: 0.0,
+                y: 0.0, // Will be positioned by the layout
+                width,
+                height,
+                inputs: node.operands(),
+                output_connects_to: Vec::new(),
+            });
+        }
+        for (name, driver_id) in &self.dag.outputs {
+            self.gates.push(GateInstance {
+                gate_type: GateType::Output(name.clone()),
+                x: 0.0,
+                y: 0.0,
                 width: 30.0,
                 height: 15.0,
-                inputs: Vec::new(),
+                inputs: vec![*driver_id],
                 output_connects_to: Vec::new(),
-            };
-            self.gates.push(input_gate);
-            gate_id_map.insert(format!("var_{}", var), self.gates.len() - 1);
+            });
         }
-        
-        // Build the circuit recursively
-        let _output_gate_id = self.build_circuit_recursive(expr, &mut gate_id_map, 80.0);
-        
+        for gate_id in 0..self.gates.len() {
+            for input_id in self.gates[gate_id].inputs.clone() {
+                self.gates[input_id].output_connects_to.push(gate_id);
+            }
+        }
+
         // Position gates in layers
         self.layout_gates();
     }
 
-    fn collect_variables(&mut self, expr: &Expr) {
-        match expr {
-            Expr::Var(name) => {
-                if !self.variables.contains(name) {
-                    self.variables.push(name.clone());
-                }
-            }
-            Expr::And(left, right) | Expr::Or(left, right) | 
-            Expr::Xor(left, right) | Expr::Nand(left, right) | 
-            Expr::Nor(left, right) | Expr::Xnor(left, right) => {
-                self.collect_variables(left);
-                self.collect_variables(right);
-            }
-            Expr::Not(inner) => {
-                self.collect_variables(inner);
-            }
-        }
+    // Logic gates in the circuit, not counting inputs, constants and outputs
+    pub fn gate_count(&self) -> usize {
+        self.dag.gate_count()
     }
 
-    fn build_circuit_recursive(&mut self, expr: &Expr, gate_id_map: &mut HashMap<String, usize>, x_pos: f64) -> usize {
-        match expr {
-            Expr::Var(name) => {
-                // Return the index of the variable input gate
-                *gate_id_map.get(&format!("var_{}", name)).unwrap()
-            }
-            Expr::And(left, right) => {
-                self.create_binary_gate(GateType::And, left, right, gate_id_map, x_pos)
-            }
-            Expr::Or(left, right) => {
-                self.create_binary_gate(GateType::Or, left, right, gate_id_map, x_pos)
-            }
-            Expr::Xor(left, right) => {
-                self.create_binary_gate(GateType::Xor, left, right, gate_id_map, x_pos)
-            }
-            Expr::Nand(left, right) => {
-                self.create_binary_gate(GateType::Nand, left, right, gate_id_map, x_pos)
+    // Layered (Sugiyama) layout. Every gate goes one layer right of its
+    // latest input, and a wire spanning several layers gets a dummy node in
+    // each one it crosses, so all edges join neighbouring layers. Layers are
+    // then reordered to cut crossings, and gates moved up or down to line up
+    // with the gates feeding them
+    fn layout_gates(&mut self) {
+        let n = self.gates.len();
+        let is_output = |gate: &GateInstance| matches!(gate.gate_type, GateType::Output(_));
+
+        // Longest-path layering. Gates come after their inputs, so one pass
+        // will do; the output terminals share a last column
+        let mut layer = vec![0; n];
+        for id in 0..n {
+            layer[id] = self.gates[id].inputs.iter().map(|&input| layer[input] + 1).max().unwrap_or(0);
+        }
+        let last = (0..n).filter(|&id| !is_output(&self.gates[id])).map(|id| layer[id]).max().unwrap_or(0) + 1;
+        for id in (0..n).filter(|&id| is_output(&self.gates[id])) {
+            layer[id] = last;
+        }
+
+        // Nodes of the layered graph are the gates, then the dummies, each
+        // knowing its neighbours in the layers either side
+        let mut preds: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut chains: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for target in 0..n {
+            for source in self.gates[target].inputs.clone() {
+                let chain = chains.entry((source, target)).or_insert_with(|| {
+                    let mut chain = Vec::new();
+                    for dummy_layer in layer[source] + 1..layer[target] {
+                        preds.push(vec![chain.last().copied().unwrap_or(source)]);
+                        layer.push(dummy_layer);
+                        chain.push(layer.len() - 1);
+                    }
+                    chain
+                });
+                preds[target].push(chain.last().copied().unwrap_or(source));
             }
-            Expr::Nor(left, right) => {
-                self.create_binary_gate(GateType::Nor, left, right, gate_id_map, x_pos)
+        }
+        let total = layer.len();
+        let mut succs: Vec<Vec<usize>> = vec![Vec::new(); total];
+        for (node, node_preds) in preds.iter().enumerate() {
+            for &pred in node_preds {
+                succs[pred].push(node);
             }
-            Expr::Xnor(left, right) => {
-                self.create_binary_gate(GateType::Xnor, left, right, gate_id_map, x_pos)
+        }
+
+        // Start from build order, which puts the inputs in order at the left,
+        // and sweep right then left, sorting each layer by the median position
+        // of its neighbours in the layer just done. Keep the best order seen
+        let mut layers: Vec<Vec<usize>> = vec![Vec::new(); last + 1];
+        for node in 0..total {
+            layers[layer[node]].push(node);
+        }
+        let mut position = positions(&layers, total);
+        let mut best = (crossings(&layers, &preds, &position), layers.clone());
+        for sweep in 0..ORDERING_SWEEPS {
+            let (order, neighbours): (Vec<usize>, _) = if sweep % 2 == 0 {
+                ((1..layers.len()).collect(), &preds)
+            } else {
+                ((0..layers.len() - 1).rev().collect(), &succs)
+            };
+            for l in order {
+                let mut keyed: Vec<(f64, usize)> = layers[l].iter().enumerate()
+                    .map(|(i, &node)| (median(&neighbours[node], &position).unwrap_or(i as f64), node))
+                    .collect();
+                keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+                layers[l] = keyed.into_iter().map(|(_, node)| node).collect();
+                for (i, &node) in layers[l].iter().enumerate() {
+                    position[node] = i as f64;
+                }
             }
-            Expr::Not(inner) => {
-                let input_gate_id = self.build_circuit_recursive(inner, gate_id_map, x_pos - 50.0);
-                
-                let gate = GateInstance {
-                    gate_type: GateType::Not,
-                    x: x_pos,
-                    y: 60.0, // Will be repositioned later
-                    width: 30.0,
-                    height: 15.0,
-                    inputs: vec![input_gate_id],
-                    output_connects_to: Vec::new(),
-                };
-                
-                self.gates.push(gate);
-                let gate_id = self.gates.len() - 1;
-                
-                // Update input gate to connect to this gate
-                self.gates[input_gate_id].output_connects_to.push(gate_id);
-                
-                gate_id
+            let count = crossings(&layers, &preds, &position);
+            if count < best.0 {
+                best = (count, layers.clone());
             }
         }
-    }
-
-    fn create_binary_gate(&mut self, gate_type: GateType, left: &Expr, right: &Expr, 
-                         gate_id_map: &mut HashMap<String, usize>, x_pos: f64) -> usize {
-        let left_gate_id = self.build_circuit_recursive(left, gate_id_map, x_pos - 50.0);
-        let right_gate_id = self.build_circuit_recursive(right, gate_id_map, x_pos - 50.0);
-        
-        let gate = GateInstance {
-            gate_type,
-            x: x_pos,
-            y: 60.0, // Will be repositioned later
-            width: 40.0,
-            height: 20.0,
-            inputs: vec![left_gate_id, right_gate_id],
-            output_connects_to: Vec::new(),
-        };
-        
-        self.gates.push(gate);
-        let gate_id = self.gates.len() - 1;
-        
-        // Update input gates to connect to this gate
-        self.gates[left_gate_id].output_connects_to.push(gate_id);
-        self.gates[right_gate_id].output_connects_to.push(gate_id);
-        
-        gate_id
-    }
+        let layers = best.1;
 
-    fn layout_gates(&mut self) {
-        // Simple layered layout based on connectivity
-        let mut layers: Vec<Vec<usize>> = Vec::new();
-        let mut visited = vec![false; self.gates.len()];
-        
-        // Find input gates (variables) - these go in layer 0
-        let mut current_layer = Vec::new();
-        for (i, gate) in self.gates.iter().enumerate() {
-            if matches!(gate.gate_type, GateType::Input(_)) {
-                current_layer.push(i);
-                visited[i] = true;
+        // Vertical placement, measured downwards to each node's centre: stack
+        // every layer, then sweep pulling nodes towards the mean of their
+        // neighbours on one side, keeping their order and spacing
+        let size = |node: usize| if node < n { self.gates[node].height } else { 0.0 };
+        let spacing = |a: usize, b: usize| if a < n && b < n { GATE_SPACING } else { WIRE_SPACING };
+        let mut centre = vec![0.0; total];
+        for nodes in &layers {
+            let mut y = 0.0;
+            for &node in nodes {
+                centre[node] = y + size(node) / 2.0;
+                y += size(node) + GATE_SPACING;
             }
         }
-        if !current_layer.is_empty() {
-            layers.push(current_layer);
-        }
-        
-        // Build subsequent layers
-        while layers.last().map_or(false, |layer| !layer.is_empty()) {
-            let mut next_layer = Vec::new();
-            
-            for &gate_id in layers.last().unwrap() {
-                for &connected_gate_id in &self.gates[gate_id].output_connects_to {
-                    if !visited[connected_gate_id] {
-                        // Check if all inputs of this gate are already visited
-                        let all_inputs_ready = self.gates[connected_gate_id].inputs.iter()
-                            .all(|&input_id| visited[input_id]);
-                        
-                        if all_inputs_ready {
-                            next_layer.push(connected_gate_id);
-                            visited[connected_gate_id] = true;
-                        }
-                    }
+        for sweep in 0..PLACEMENT_SWEEPS {
+            let neighbours = if sweep % 2 == 0 { &preds } else { &succs };
+            let order: Vec<&Vec<usize>> = if sweep % 2 == 0 { layers.iter().collect() } else { layers.iter().rev().collect() };
+            for nodes in order {
+                let desired: Vec<f64> = nodes.iter()
+                    .map(|&node| match neighbours[node].len() {
+                        0 => centre[node],
+                        count => neighbours[node].iter().map(|&other| centre[other]).sum::<f64>() / count as f64,
+                    })
+                    .collect();
+                let gaps: Vec<f64> = (0..nodes.len())
+                    .map(|i| match i {
+                        0 => 0.0,
+                        _ => (size(nodes[i - 1]) + size(nodes[i])) / 2.0 + spacing(nodes[i - 1], nodes[i]),
+                    })
+                    .collect();
+                for (&node, y) in nodes.iter().zip(place(&desired, &gaps)) {
+                    centre[node] = y;
                 }
             }
-            
-            if !next_layer.is_empty() {
-                layers.push(next_layer);
-            } else {
-                break;
-            }
         }
-        
-        // Position gates based on layers
-        for (layer_idx, layer) in layers.iter().enumerate() {
-            let x = 30.0 + (layer_idx as f64 * 70.0);
-            let layer_height = layer.len() as f64 * 30.0;
-            let start_y = (120.0 - layer_height) / 2.0;
-            
-            for (i, &gate_id) in layer.iter().enumerate() {
-                self.gates[gate_id].x = x;
-                self.gates[gate_id].y = start_y + (i as f64 * 30.0);
-            }
+
+        // Canvas coordinates, with y going up, padded for the labels and
+        // widened or heightened to keep the usual proportions
+        let column = |l: usize| l as f64 * LAYER_SPACING;
+        for (id, gate) in self.gates.iter_mut().enumerate() {
+            gate.x = column(layer[id]);
+            gate.y = -centre[id] - gate.height / 2.0;
+        }
+        let mut left = f64::MAX;
+        let mut right = f64::MIN;
+        let mut bottom = f64::MAX;
+        let mut top = f64::MIN;
+        for gate in &self.gates {
+            left = left.min(gate.x);
+            right = right.max(gate.x + gate.width);
+            bottom = bottom.min(gate.y);
+            top = top.max(gate.y + gate.height);
         }
+        for y in &centre[n..] {
+            bottom = bottom.min(-y);
+            top = top.max(-y);
+        }
+        let (left, right, bottom, top) = (left - 20.0, right + 40.0, bottom - 10.0, top + 10.0);
+        let width = (right - left).max((top - bottom) * ASPECT).max(MIN_WIDTH);
+        let height = width / ASPECT;
+        let x_offset = left - (width - (right - left)) / 2.0;
+        let y_offset = bottom - (height - (top - bottom)) / 2.0;
+        for gate in &mut self.gates {
+            gate.x -= x_offset;
+            gate.y -= y_offset;
+        }
+        self.extent = [width, height];
+
+        // A long wire runs straight through the layers its dummies are in
+        self.routes = chains.into_iter()
+            .map(|(edge, chain)| {
+                let points = chain.iter()
+                    .flat_map(|&dummy| {
+                        let (x, y) = (column(layer[dummy]) - x_offset, -centre[dummy] - y_offset);
+                        [(x, y), (x + DUMMY_WIDTH, y)]
+                    })
+                    .collect();
+                (edge, points)
+            })
+            .collect();
     }
 
     pub fn pan(&mut self, dx: f64, dy: f64) {
@@ -254,9 +399,11 @@ impl LogicGatesViewer {
         }
     }
 
-    pub fn render(&self, f: &mut Frame, area: Rect) {
+    pub fn render(&self, f: &mut Frame, area: Rect, title: &str) {
+        self.area.set(area);
+        let (x_bounds, y_bounds) = self.bounds();
         let canvas = Canvas::default()
-            .block(Block::default().borders(Borders::ALL).title("Logic Circuit"))
+            .block(Block::default().borders(Borders::ALL).title(title.to_string()))
             .paint(|ctx| {
                 ctx.layer();
 
@@ -279,14 +426,18 @@ impl LogicGatesViewer {
                             target_gate.y + target_gate.height / 2.0
                         };
                         
-                        let (x1, y1) = transform(gate_output_x, gate_output_y);
-                        let (x2, y2) = transform(target_input_x, actual_target_y);
-                        
-                        // Draw connection line
-                        ctx.draw(&ratatui::widgets::canvas::Line {
-                            x1, y1, x2, y2,
-                            color: Color::Green,
-                        });
+                        // Draw connection line through its bends, coloured by its level when simulating
+                        let mut points = vec![(gate_output_x, gate_output_y)];
+                        points.extend(self.routes.get(&(gate_id, target_gate_id)).into_iter().flatten());
+                        points.push((target_input_x, actual_target_y));
+                        for pair in points.windows(2) {
+                            let (x1, y1) = transform(pair[0].0, pair[0].1);
+                            let (x2, y2) = transform(pair[1].0, pair[1].1);
+                            ctx.draw(&ratatui::widgets::canvas::Line {
+                                x1, y1, x2, y2,
+                                color: self.level_color(self.values[gate_id]),
+                            });
+                        }
                     }
                 }
 
@@ -309,6 +460,11 @@ impl LogicGatesViewer {
                             // Draw variable label
                             ctx.print(x + width / 4.0, y + height / 2.0, name.clone());
                         }
+                        GateType::Const(value) => Self::draw_const_gate(ctx, x, y, width, height, value),
+                        GateType::Output(ref name) => {
+                            Self::draw_output_gate(ctx, x, y, width, height);
+                            ctx.print(x + width / 3.0, y + height / 2.0, name.clone());
+                        }
                     }
                 }
 
@@ -323,16 +479,43 @@ impl LogicGatesViewer {
                         GateType::Nor => "NOR",
                         GateType::Xor => "XOR",
                         GateType::Xnor => "XNOR",
-                        GateType::Input(_) => "",
+                        GateType::Input(_) | GateType::Output(_) => "",
+                        GateType::Const(true) => "VCC",
+                        GateType::Const(false) => "GND",
                     };
                     if !label.is_empty() {
                         ctx.print(label_x - label.len() as f64 * 2.0, label_y, label.to_string());
                     }
                 }
+
+                // The level at every gate output, and the key that flips each input
+                if self.simulating {
+                    for (gate_id, gate) in self.gates.iter().enumerate() {
+                        let value = self.values[gate_id];
+                        let text = Line::styled(if value { "1" } else { "0" }, self.level_color(value));
+                        let (x, y) = match gate.gate_type {
+                            GateType::Output(_) => transform(gate.x + gate.width * 1.1, gate.y + gate.height / 2.0),
+                            _ => transform(gate.x + gate.width * 1.2, gate.y + gate.height / 2.0 + 3.0),
+                        };
+                        ctx.print(x, y, text);
+                    }
+                    for (index, name) in self.dag.inputs.iter().enumerate().take(10) {
+                        let Some(gate) = self.gates.iter().find(|g| g.gate_type == GateType::Input(name.clone())) else { continue };
+                        let (x, y) = transform(gate.x - gate.width * 0.5, gate.y + gate.height / 2.0);
+                        ctx.print(x, y, Line::styled(format!("[{}]", (index + 1) % 10), Color::Yellow));
+                    }
+                }
+
+                // Name the gates that drive `let` wires at their output
+                for (gate_id, name) in &self.wire_labels {
+                    let gate = &self.gates[*gate_id];
+                    let (wire_x, wire_y) = transform(gate.x + gate.width * 1.25, gate.y + gate.height / 2.0 + 3.0);
+                    ctx.print(wire_x, wire_y, ratatui::text::Line::styled(name.clone(), Color::Yellow));
+                }
             })
             .marker(symbols::Marker::Braille)
-            .x_bounds([0.0, 300.0])
-            .y_bounds([0.0, 120.0]);
+            .x_bounds(x_bounds)
+            .y_bounds(y_bounds);
 
         f.render_widget(canvas, area);
     }
@@ -363,6 +546,73 @@ impl LogicGatesViewer {
         });
     }
 
+    // Output terminal - an arrow-shaped flag pointing right
+    fn draw_output_gate(ctx: &mut ratatui::widgets::canvas::Context, x: f64, y: f64, width: f64, height: f64) {
+        let tip_x = x + width;
+        let center_y = y + height / 2.0;
+        let shoulder_x = x + width * 0.75;
+
+        // Input line
+        ctx.draw(&ratatui::widgets::canvas::Line {
+            x1: x - width * 0.2, y1: center_y, x2: x, y2: center_y, color: Color::Magenta,
+        });
+        // Flag outline
+        ctx.draw(&ratatui::widgets::canvas::Line {
+            x1: x, y1: y, x2: x, y2: y + height, color: Color::Magenta,
+        });
+        ctx.draw(&ratatui::widgets::canvas::Line {
+            x1: x, y1: y + height, x2: shoulder_x, y2: y + height, color: Color::Magenta,
+        });
+        ctx.draw(&ratatui::widgets::canvas::Line {
+            x1: shoulder_x, y1: y + height, x2: tip_x, y2: center_y, color: Color::Magenta,
+        });
+        ctx.draw(&ratatui::widgets::canvas::Line {
+            x1: tip_x, y1: center_y, x2: shoulder_x, y2: y, color: Color::Magenta,
+        });
+        ctx.draw(&ratatui::widgets::canvas::Line {
+            x1: shoulder_x, y1: y, x2: x, y2: y, color: Color::Magenta,
+        });
+    }
+
+    // Constant source - a supply rail (tied high) or a ground symbol (tied low)
+    fn draw_const_gate(ctx: &mut ratatui::widgets::canvas::Context, x: f64, y: f64, width: f64, height: f64, value: bool) {
+        let color = if value { Color::Red } else { Color::Blue };
+        let stem_x = x + width * 0.5;
+        let center_y = y + height / 2.0;
+
+        if value {
+            // Stem up to a horizontal rail
+            ctx.draw(&ratatui::widgets::canvas::Line {
+                x1: stem_x, y1: center_y, x2: stem_x, y2: y + height, color,
+            });
+            ctx.draw(&ratatui::widgets::canvas::Line {
+                x1: x + width * 0.2, y1: y + height, x2: x + width * 0.8, y2: y + height, color,
+            });
+        } else {
+            // Stem down to three shrinking ground bars
+            ctx.draw(&ratatui::widgets::canvas::Line {
+                x1: stem_x, y1: center_y, x2: stem_x, y2: y, color,
+            });
+            for i in 0..3 {
+                let half = width * (0.3 - i as f64 * 0.1);
+                let bar_y = y - i as f64 * height * 0.15;
+                ctx.draw(&ratatui::widgets::canvas::Line {
+                    x1: stem_x - half, y1: bar_y, x2: stem_x + half, y2: bar_y, color,
+                });
+            }
+        }
+        ctx.print(x + width * 0.1, center_y, if value { "1" } else { "0" });
+
+        // Output line
+        ctx.draw(&ratatui::widgets::canvas::Line {
+            x1: stem_x,
+            y1: center_y,
+            x2: x + width * 1.3,
+            y2: center_y,
+            color,
+        });
+    }
+
     // AND Gate - shaped like a D
     fn draw_and_gate(ctx: &mut ratatui::widgets::canvas::Context, x: f64, y: f64, width: f64, height: f64) {
         // Left vertical line
@@ -627,3 +877,92 @@ impl LogicGatesViewer {
         }
     }
 }
+
+// Index of every node within its layer
+fn positions(layers: &[Vec<usize>], total: usize) -> Vec<f64> {
+    let mut position = vec![0.0; total];
+    for nodes in layers {
+        for (i, &node) in nodes.iter().enumerate() {
+            position[node] = i as f64;
+        }
+    }
+    position
+}
+
+// Median position of some nodes, halfway between the middle two for an even
+// count, or None if there are none
+fn median(nodes: &[usize], position: &[f64]) -> Option<f64> {
+    let mut values: Vec<f64> = nodes.iter().map(|&node| position[node]).collect();
+    values.sort_by(f64::total_cmp);
+    match values.len() {
+        0 => None,
+        len if len % 2 == 1 => Some(values[len / 2]),
+        len => Some((values[len / 2 - 1] + values[len / 2]) / 2.0),
+    }
+}
+
+// Pairs of edges that cross between neighbouring layers. With the edges
+// sorted by their upper end, two cross exactly when their lower ends are out
+// of order, so this counts inversions
+fn crossings(layers: &[Vec<usize>], preds: &[Vec<usize>], position: &[f64]) -> usize {
+    let mut count = 0;
+    for nodes in layers.iter().skip(1) {
+        let mut edges: Vec<(f64, f64)> = nodes.iter()
+            .flat_map(|&node| preds[node].iter().map(move |&pred| (position[pred], position[node])))
+            .collect();
+        edges.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        let mut lower: Vec<f64> = edges.into_iter().map(|(_, lower)| lower).collect();
+        count += inversions(&mut lower);
+    }
+    count
+}
+
+// Pairs out of order, counted while merge sorting
+fn inversions(values: &mut [f64]) -> usize {
+    if values.len() < 2 {
+        return 0;
+    }
+    let middle = values.len() / 2;
+    let mut count = inversions(&mut values[..middle]) + inversions(&mut values[middle..]);
+    let mut merged = Vec::with_capacity(values.len());
+    let (mut i, mut j) = (0, middle);
+    while i < middle && j < values.len() {
+        if values[j] < values[i] {
+            count += middle - i;
+            merged.push(values[j]);
+            j += 1;
+        } else {
+            merged.push(values[i]);
+            i += 1;
+        }
+    }
+    merged.extend_from_slice(&values[i..middle]);
+    merged.extend_from_slice(&values[j..]);
+    values.copy_from_slice(&merged);
+    count
+}
+
+// Positions in order, each at least `gaps[i]` past the one before, as close
+// to `desired` as they can be in the least-squares sense. With the gaps
+// taken out this is isotonic regression, solved by pooling adjacent blocks
+// that are out of order into their mean
+fn place(desired: &[f64], gaps: &[f64]) -> Vec<f64> {
+    let mut offset = 0.0;
+    let offsets: Vec<f64> = gaps.iter().map(|gap| { offset += gap; offset }).collect();
+    let mut blocks: Vec<(f64, usize)> = Vec::new();  // sum and count of each pool
+    for (y, offset) in desired.iter().zip(&offsets) {
+        blocks.push((y - offset, 1));
+        while let [.., (sum_a, count_a), (sum_b, count_b)] = blocks[..]
+            && sum_a / count_a as f64 > sum_b / count_b as f64
+        {
+            blocks.pop();
+            *blocks.last_mut().unwrap() = (sum_a + sum_b, count_a + count_b);
+        }
+    }
+    blocks.iter()
+        .flat_map(|&(sum, count)| std::iter::repeat_n(sum / count as f64, count))
+        .zip(offsets)
+        .map(|(y, offset)| y + offset)
+        .collect()
+}
+
@@ -4,27 +4,52 @@ mod eval;
 mod truth_table;
 mod k_map;
 mod logic_gates;
+mod minimize;
+mod espresso;
+mod pla;
+mod bdd;
+mod bdd_view;
+mod equivalence;
+mod sat;
+mod dimacs;
+mod normal_form;
+mod derivation;
+mod proof;
+mod dag;
+#[cfg(test)]
+mod testing;
 
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Style},
     text::{Line, Text},
-    widgets::{Block, Borders, Paragraph, Tabs},
+    widgets::{Block, Borders, Paragraph, Tabs, Wrap},
     Terminal,
 };
 use crossterm::{
-    event::{self, Event, KeyCode, MouseEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEventKind},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     execute,
 };
-use parser::parse_expr;
-use truth_table::truth_table;
-use k_map::k_map;
+use parser::{parse_chain, parse_system, Dialect, ParseError};
+use truth_table::{output_values, output_variables, system_variables, truth_table, MAX_TABLE_VARIABLES};
+use k_map::KMapView;
 use logic_gates::LogicGatesViewer;
+use minimize::{minimized_system, simplification, Form, MAX_EXACT_VARIABLES};
+use pla::{parse_pla, Pla};
+use bdd::analysis;
+use bdd_view::BddViewer;
+use equivalence::equivalence;
+use sat::{sat_report, tseitin};
+use dimacs::{parse_dimacs, plain_cnf, write_dimacs};
+use normal_form::NormalFormsView;
+use derivation::{derivation_lines, plain_text};
+use proof::proof_lines;
+use expr::System;
 use std::io;
 
-fn expr_input() -> Result<String, io::Error> {
+fn expr_input() -> Result<(String, Dialect), io::Error> {
     //Set up the terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -34,6 +59,10 @@ fn expr_input() -> Result<String, io::Error> {
 
     //Init input state
     let mut input = String::new();
+    let mut error: Option<ParseError> = None;
+    let mut dialect = Dialect::default();
+    let mut proving = false;     //Checking a chain `a = b = c` instead of reading an expression
+    let mut proof: Option<Vec<Line>> = None;
 
     loop {
         terminal.draw(|f| {
@@ -45,7 +74,8 @@ fn expr_input() -> Result<String, io::Error> {
                 .constraints([
                     Constraint::Percentage(30),   //Top Padding
                     Constraint::Length(3),        //Input field Height
-                    Constraint::Length(6),        //Instructions
+                    Constraint::Length(4),        //Parse error
+                    Constraint::Length(12),       //Instructions
                     Constraint::Percentage(30),   //Bottom Padding
                 ])
                 .split(size);
@@ -63,7 +93,11 @@ fn expr_input() -> Result<String, io::Error> {
             let block = Block::default()
                 .borders(Borders::ALL)
                 .style(Style::default().fg(Color::White))
-                .title("Boolean Expression");
+                .title(if proving {
+                    format!("Proof: expressions joined by = ({} notation, Tab to switch, F2 for an expression)", dialect.name())
+                } else {
+                    format!("Boolean Expression ({} notation, Tab to switch, F2 to check a proof)", dialect.name())
+                });
 
             let paragraph = Paragraph::new(Text::from(input.as_str()))
                 .block(block)
@@ -71,19 +105,63 @@ fn expr_input() -> Result<String, io::Error> {
 
             f.render_widget(paragraph, input_area);
 
+            // Show where the expression stops parsing, right under the input
+            if let Some(err) = &error {
+                let error_area = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([
+                        Constraint::Percentage(20),
+                        Constraint::Percentage(60),
+                        Constraint::Percentage(20),
+                    ])
+                    .split(chunks[2])[1];
+
+                let caret = input[..err.offset].chars().count();
+                let error_text = Paragraph::new(Text::from(vec![
+                    Line::from(format!("{}^", " ".repeat(caret))),
+                    Line::from(format!("column {}: {}", err.column, err.message())),
+                ]))
+                .block(Block::default().borders(Borders::LEFT | Borders::RIGHT | Borders::BOTTOM))
+                .style(Style::default().fg(Color::Red));
+
+                f.render_widget(error_text, error_area);
+            }
+
             // Instructions
             let instructions = Paragraph::new(Text::from(vec![
                 Line::from("Enter a boolean expression using:"),
                 Line::from("Variables: A, B, C, etc."),
-                Line::from("Operators: & (AND), | (OR), ! (NOT), ^ (XOR)"),
-                Line::from("Parentheses: ( ) for grouping"),
-                Line::from("Example: A & B | !C"),
+                Line::from("Operators: & * · ∧ (AND), | + ∨ (OR), ! ~ ¬ ' (NOT), ^ ⊕ (XOR)"),
+                Line::from("         !& ↑ (NAND), !| ↓ (NOR), !^ ⊙ (XNOR), postfix ' as in (A+B)'"),
+                Line::from("         -> (IMPLIES), <- (CONVERSE), <-> (IFF), constants 0 1"),
+                Line::from("Parentheses: ( ) for grouping, several outputs: S = A ^ B; C = A & B"),
+                Line::from("Wires: let t = A & B; F = t | (t ^ C)"),
+                Line::from("Minterms: F(A,B,C) = Σm(1,3,7) + d(0,2), or m(1,3,7) d(0,2), ΠM(...) for maxterms"),
+                Line::from("Don't-cares: F = A ^ B; dc F = A & B"),
+                Line::from(match dialect {
+                    Dialect::Standard => "Example: A & B | !C",
+                    Dialect::Textbook => "Example: AB' + C(A+B)'  (single-letter names, AND by juxtaposition)",
+                }),
                 Line::from("Press Enter to continue, Esc to exit"),
             ]))
             .block(Block::default().borders(Borders::ALL).title("Instructions"))
             .style(Style::default().fg(Color::Gray));
             
-            f.render_widget(instructions, chunks[2]);
+            // A proof is checked as it is typed, in place of the instructions
+            if proving {
+                let text = proof.clone().unwrap_or_else(|| vec![
+                    Line::from("Write a simplification as expressions joined by =, for example"),
+                    Line::from("A & B | A & !B = A & (B | !B) = A & 1 = A"),
+                    Line::from("Every step is checked, and named after the law that justifies it where there is one."),
+                    Line::from("Press Esc to exit"),
+                ]);
+                let report = Paragraph::new(text)
+                    .block(Block::default().borders(Borders::ALL).title("Proof"))
+                    .wrap(Wrap { trim: false });
+                f.render_widget(report, chunks[3]);
+            } else {
+                f.render_widget(instructions, chunks[3]);
+            }
 
         })?;
 
@@ -92,7 +170,10 @@ fn expr_input() -> Result<String, io::Error> {
             match key.code {
                 KeyCode::Char(c) => input.push(c),
                 KeyCode::Backspace => { input.pop(); },
-                KeyCode::Enter => break,
+                KeyCode::Tab => dialect = dialect.toggle(),
+                KeyCode::F(2) => proving = !proving,
+                // Only accept an expression that parses (or an empty one, to quit)
+                KeyCode::Enter if error.is_none() && !proving => break,
                 KeyCode::Esc => {
                     // Clean up and exit
                     disable_raw_mode()?;
@@ -102,6 +183,19 @@ fn expr_input() -> Result<String, io::Error> {
                 }
                 _ => {}
             }
+
+            error = None;
+            proof = None;
+            if input.trim().is_empty() {
+                // Nothing to check yet
+            } else if proving {
+                match parse_chain(&input, dialect) {
+                    Ok(exprs) => proof = Some(proof_lines(&exprs).1),
+                    Err(err) => error = Some(err),
+                }
+            } else {
+                error = parse_system(&input, dialect).err();
+            }
         }
     }
 
@@ -110,27 +204,51 @@ fn expr_input() -> Result<String, io::Error> {
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
-    Ok(input)
+    Ok((input, dialect))
 }
 
-fn show_tabs(expr_str: &str) -> Result<(), io::Error> {
-    let expression = parse_expr(expr_str.trim());
-    let table_str = truth_table(&expression);
+fn show_tabs(expr_str: &str, system: System) -> Result<(), io::Error> {
+    let mut show_wires = false;  //Intermediate wire columns in the truth table
+    // Too wide for a truth table: list satisfying assignments instead
+    let use_sat = system_variables(&system).len() > MAX_TABLE_VARIABLES;
+    let mut table_str = if use_sat { sat_report(&system) } else { truth_table(&system, show_wires) };
 
     enable_raw_mode()?;
 
+    // Mouse capture for zooming and clicking inputs on the circuit
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let tabs = ["Truth Table", "K-Map", "Logic Circuit"];
+    let tabs = ["Truth Table", "K-Map", "Logic Circuit", "Simplified", "Analysis", "BDD", "Normal Forms", "Derivation"];
+    let simplified_str = simplification(&system);
+    let analysis_str = analysis(&system);
+    let derivation = derivation_lines(&system);
     let mut active_tab = 0;
-    let mut scroll: u16 = 0;     //For scrolling
+    let mut scroll = vec![0u16; tabs.len()];  //For scrolling, per tab
+    let mut kmap_output = 0;     //Which output the K-Map shows
+    let mut kmaps: Vec<KMapView> = system.outputs.iter()
+        .map(|output| {
+            let vars = output_variables(&system, output);
+            // Maps past MAX_EXACT_VARIABLES are not drawn, so skip evaluating them
+            let values = if vars.len() <= MAX_EXACT_VARIABLES {
+                output_values(&system, output, &vars)
+            } else {
+                Vec::new()
+            };
+            KMapView::new(values, vars)
+        })
+        .collect();
     let mut logic_gates_viewer = LogicGatesViewer::new();
+    let circuit_views = ["Original", "Minimal SOP", "Minimal POS", "Shared terms"];
+    let mut circuit_view = 0;    //Which form the circuit is drawn from
+    let mut circuit_label = circuit_views[0].to_string();
+    let mut bdd_viewer = BddViewer::new(&system);
+    let mut normal_forms = NormalFormsView::new(&system);
     
     // Set the expression for the logic gates viewer
-    logic_gates_viewer.set_expression(expression.clone());
+    logic_gates_viewer.set_system(system.clone());
 
     loop {
         terminal.draw(|f| {
@@ -160,129 +278,311 @@ fn show_tabs(expr_str: &str) -> Result<(), io::Error> {
 
             match active_tab {
                 0 => {
+                    let title = if use_sat {
+                        "Satisfying Assignments (SAT solver)".to_string()
+                    } else if system.definitions.is_empty() {
+                        "Truth Table".to_string()
+                    } else {
+                        format!("Truth Table ('i' to {} wires)", if show_wires { "hide" } else { "show" })
+                    };
                     let content = Paragraph::new(table_str.clone())
-                        .block(Block::default().borders(Borders::ALL).title("Truth Table"))
+                        .block(Block::default().borders(Borders::ALL).title(title))
                         .alignment(ratatui::layout::Alignment::Center)
-                        .scroll((scroll, 0));
+                        .scroll((scroll[0], 0));
                     f.render_widget(content, chunks[2]);
                 }
                 1 => {
-                    let content = Paragraph::new(k_map(&expression))
-                        .block(Block::default().borders(Borders::ALL).title("K-Map"))
-                        .alignment(ratatui::layout::Alignment::Center);
-                    f.render_widget(content, chunks[2]);
+                    let output = &system.outputs[kmap_output];
+                    let title = if system.outputs.len() > 1 {
+                        format!("K-Map: {} (Tab for next output)", output.name)
+                    } else {
+                        "K-Map".to_string()
+                    };
+                    kmaps[kmap_output].render(f, chunks[2], &title);
                 }
                 2 => {
-                    logic_gates_viewer.render(f, chunks[2]);
+                    let title = if logic_gates_viewer.simulating {
+                        format!("Logic Circuit: {}, {} gates (simulating: 1-9 and 0 or a click flip an input, 'v' to stop)", circuit_label, logic_gates_viewer.gate_count())
+                    } else {
+                        format!("Logic Circuit: {}, {} gates ('m' to switch, 'v' to simulate)", circuit_label, logic_gates_viewer.gate_count())
+                    };
+                    logic_gates_viewer.render(f, chunks[2], &title);
+                }
+                3 => {
+                    let content = Paragraph::new(simplified_str.clone())
+                        .block(Block::default().borders(Borders::ALL).title("Simplified"))
+                        .scroll((scroll[3], 0));
+                    f.render_widget(content, chunks[2]);
+                }
+                4 => {
+                    let content = Paragraph::new(analysis_str.clone())
+                        .block(Block::default().borders(Borders::ALL).title("Analysis (BDD)"))
+                        .scroll((scroll[4], 0));
+                    f.render_widget(content, chunks[2]);
+                }
+                5 => bdd_viewer.render(f, chunks[2]),
+                6 => normal_forms.render(f, chunks[2]),
+                7 => {
+                    let content = Paragraph::new(derivation.clone())
+                        .block(Block::default().borders(Borders::ALL).title("Derivation"))
+                        .scroll((scroll[7], 0));
+                    f.render_widget(content, chunks[2]);
                 }
                 _ => {}
             }
         })?;
 
-        if let Event::Key(key) = event::read()? {
-            match key.code {
-                KeyCode::Left => {
-                    if active_tab > 0 { active_tab -= 1; }
+        match event::read()? {
+            Event::Key(key) => match key.code {
+                KeyCode::Left if active_tab > 0 => active_tab -= 1,
+                KeyCode::Right if active_tab < tabs.len() - 1 => active_tab += 1,
+                KeyCode::Up if matches!(active_tab, 0 | 3 | 4 | 7) => {
+                    scroll[active_tab] = scroll[active_tab].saturating_sub(1);
                 }
-                KeyCode::Right => {
-                    if active_tab < tabs.len() - 1 {
-                        active_tab += 1;
-                    }
+                KeyCode::Down if matches!(active_tab, 0 | 3 | 4 | 7) => scroll[active_tab] += 1,
+                KeyCode::Char('i') if active_tab == 0 && !use_sat => {
+                    show_wires = !show_wires;
+                    table_str = truth_table(&system, show_wires);
                 }
-                KeyCode::Up => {
-                    if active_tab == 0 && scroll > 0 {
-                        scroll -= 1;
-                    }
+                KeyCode::Up if active_tab == 1 => kmaps[kmap_output].select_prev(),
+                KeyCode::Down if active_tab == 1 => kmaps[kmap_output].select_next(),
+                KeyCode::Char('s') if active_tab == 1 => kmaps[kmap_output].cycle_split(),
+                KeyCode::Char('v') if active_tab == 1 => kmaps[kmap_output].cycle_entered(),
+                KeyCode::PageUp if active_tab == 1 => kmaps[kmap_output].scroll_submaps(false),
+                KeyCode::PageDown if active_tab == 1 => kmaps[kmap_output].scroll_submaps(true),
+                KeyCode::Tab if active_tab == 1 => {
+                    kmap_output = (kmap_output + 1) % system.outputs.len();
                 }
-                KeyCode::Down => {
-                    if active_tab == 0 {
-                        scroll += 1;
-                    }
+                KeyCode::BackTab if active_tab == 1 => {
+                    kmap_output = (kmap_output + system.outputs.len() - 1) % system.outputs.len();
                 }
-                KeyCode::Char('r') => {
-                    if active_tab == 2 {
-                        // Reset pan and zoom
-                        logic_gates_viewer.pan_x = 0.0;
-                        logic_gates_viewer.pan_y = 0.0;
-                        logic_gates_viewer.zoom = 1.0;
-                    }
+                KeyCode::Char('m') if active_tab == 2 => {
+                    circuit_view = (circuit_view + 1) % circuit_views.len();
+                    circuit_label = circuit_views[circuit_view].to_string();
+                    logic_gates_viewer.set_system(match circuit_view {
+                        1 => minimized_system(&system, Form::Sop),
+                        2 => minimized_system(&system, Form::Pos),
+                        3 => minimized_system(&system, Form::Shared),
+                        _ => system.clone(),
+                    });
                 }
-                KeyCode::Char('w') => {
-                    if active_tab == 2 {
-                        logic_gates_viewer.pan(0.0, -5.0);
-                    }
+                KeyCode::Char('v') if active_tab == 2 => logic_gates_viewer.toggle_simulation(),
+                // Keys 1 to 9 flip the first nine inputs and 0 the tenth
+                KeyCode::Char(c @ '0'..='9') if active_tab == 2 => {
+                    let digit = c.to_digit(10).unwrap() as usize;
+                    logic_gates_viewer.toggle_input((digit + 9) % 10);
                 }
-                KeyCode::Char('s') => {
-                    if active_tab == 2 {
-                        logic_gates_viewer.pan(0.0, 5.0);
-                    }
-                }
-                KeyCode::Char('a') => {
-                    if active_tab == 2 {
-                        logic_gates_viewer.pan(-5.0, 0.0);
-                    }
+                KeyCode::Char('r') if active_tab == 2 => {
+                    // Reset pan and zoom
+                    logic_gates_viewer.pan_x = 0.0;
+                    logic_gates_viewer.pan_y = 0.0;
+                    logic_gates_viewer.zoom = 1.0;
                 }
-                KeyCode::Char('d') => {
-                    if active_tab == 2 {
-                        logic_gates_viewer.pan(5.0, 0.0);
-                    }
+                KeyCode::Char('w') if active_tab == 2 => logic_gates_viewer.pan(0.0, -5.0),
+                KeyCode::Char('s') if active_tab == 2 => logic_gates_viewer.pan(0.0, 5.0),
+                KeyCode::Char('a') if active_tab == 2 => logic_gates_viewer.pan(-5.0, 0.0),
+                KeyCode::Char('d') if active_tab == 2 => logic_gates_viewer.pan(5.0, 0.0),
+                KeyCode::Char('+') | KeyCode::Char('=') if active_tab == 2 => {
+                    logic_gates_viewer.zoom_in();
                 }
-                KeyCode::Char('+') | KeyCode::Char('=') => {
-                    if active_tab == 2 {
-                        logic_gates_viewer.zoom_in();
+                KeyCode::Char('-') if active_tab == 2 => logic_gates_viewer.zoom_out(),
+                KeyCode::Up if active_tab == 6 => normal_forms.select_prev(),
+                KeyCode::Down if active_tab == 6 => normal_forms.select_next(),
+                // Draw the selected form on the Logic Circuit tab
+                KeyCode::Enter if active_tab == 6 => {
+                    if let Some((label, expr)) = normal_forms.selected_expr() {
+                        logic_gates_viewer.set_expression(expr);
+                        circuit_label = label;
+                        active_tab = 2;
                     }
                 }
-                KeyCode::Char('-') => {
-                    if active_tab == 2 {
-                        logic_gates_viewer.zoom_out();
-                    }
+                KeyCode::Up if active_tab == 5 => bdd_viewer.select_prev(),
+                KeyCode::Down if active_tab == 5 => bdd_viewer.select_next(),
+                KeyCode::Char('<') | KeyCode::Char(',') if active_tab == 5 => bdd_viewer.move_selected(false),
+                KeyCode::Char('>') | KeyCode::Char('.') if active_tab == 5 => bdd_viewer.move_selected(true),
+                KeyCode::Char('o') if active_tab == 5 => bdd_viewer.sift(),
+                KeyCode::Char('n') if active_tab == 5 => bdd_viewer.reset_order(),
+                KeyCode::Char('r') if active_tab == 5 => {
+                    bdd_viewer.pan_x = 0.0;
+                    bdd_viewer.pan_y = 0.0;
+                    bdd_viewer.zoom = 1.0;
                 }
+                KeyCode::Char('w') if active_tab == 5 => bdd_viewer.pan(0.0, -5.0),
+                KeyCode::Char('s') if active_tab == 5 => bdd_viewer.pan(0.0, 5.0),
+                KeyCode::Char('a') if active_tab == 5 => bdd_viewer.pan(-5.0, 0.0),
+                KeyCode::Char('d') if active_tab == 5 => bdd_viewer.pan(5.0, 0.0),
+                KeyCode::Char('+') | KeyCode::Char('=') if active_tab == 5 => bdd_viewer.zoom_in(),
+                KeyCode::Char('-') if active_tab == 5 => bdd_viewer.zoom_out(),
                 KeyCode::Esc => break,
                 _ => {}
-            }
-        }
-        
-        // Handle mouse events for Logic Gates tab
-        if let Event::Mouse(mouse_event) = event::read()? {
-            if active_tab == 2 {
-                match mouse_event.kind {
-                    MouseEventKind::ScrollUp => {
-                        logic_gates_viewer.zoom_in();
-                    }
-                    MouseEventKind::ScrollDown => {
-                        logic_gates_viewer.zoom_out();
-                    }
-                    _ => {}
-                }
-            }
+            },
+            // Handle mouse events for Logic Gates tab
+            Event::Mouse(mouse_event) if active_tab == 2 => match mouse_event.kind {
+                MouseEventKind::ScrollUp => logic_gates_viewer.zoom_in(),
+                MouseEventKind::ScrollDown => logic_gates_viewer.zoom_out(),
+                MouseEventKind::Down(MouseButton::Left) => logic_gates_viewer.click(mouse_event.column, mouse_event.row),
+                _ => {}
+            },
+            Event::Mouse(mouse_event) if active_tab == 5 => match mouse_event.kind {
+                MouseEventKind::ScrollUp => bdd_viewer.zoom_in(),
+                MouseEventKind::ScrollDown => bdd_viewer.zoom_out(),
+                _ => {}
+            },
+            _ => {}
         }
     }
 
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
     terminal.show_cursor()?;
 
     Ok(())
 }
 
+// A DIMACS file if it ends in .cnf, a Berkeley PLA file otherwise
+fn load_file(path: &str) -> Result<System, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    if path.ends_with(".cnf") {
+        parse_dimacs(&text).map_err(|err| format!("{}: {}", path, err))
+    } else {
+        parse_pla(&text).map(|pla| pla.to_system()).map_err(|err| format!("{}: {}", path, err))
+    }
+}
+
+// The command-line modes read a file, or an expression if no such file exists
+fn load_source(source: &str) -> Result<System, String> {
+    if std::path::Path::new(source).is_file() {
+        load_file(source)
+    } else {
+        parse_system(source, Dialect::Standard).map_err(|err| err.to_string())
+    }
+}
+
+// DIMACS of the single output of a source, plain or through Tseitin
+fn cnf_of(source: &str, plain: bool) -> Result<String, String> {
+    let system = load_source(source)?;
+    if system.outputs.len() != 1 {
+        return Err(format!("a CNF file holds one output, and this has {}", system.outputs.len()));
+    }
+    let encoding = if plain { plain_cnf(&system, 0)? } else { tseitin(&system, 0) };
+    Ok(write_dimacs(&encoding))
+}
+
+// Every command-line error exits with 2, leaving 1 to mean "no" in --equiv
+// and --prove
+fn fail(err: impl std::fmt::Display) -> ! {
+    eprintln!("Error: {}", err);
+    std::process::exit(2);
+}
+
+fn usage() {
+    println!("Usage: kiroku                      enter an expression interactively");
+    println!("       kiroku FILE.pla|FILE.cnf    open a Berkeley PLA or DIMACS CNF file");
+    println!("       kiroku --espresso SOURCE    print a minimised PLA of a .pla file or an expression");
+    println!("       kiroku --cnf SOURCE         print the CNF of a single output in DIMACS, over its inputs only");
+    println!("       kiroku --tseitin SOURCE     the same through the Tseitin transformation, with gate variables");
+    println!("       kiroku --analyse SOURCE     satisfiability, model counts and equivalent outputs, from a BDD");
+    println!("       kiroku --derive SOURCE      simplify step by step, naming the law used at each step");
+    println!("       kiroku --prove CHAIN       check each step of a chain like \"A & (B | !B) = A & 1 = A\"");
+    println!("       kiroku --equiv FIRST SECOND check two expressions or .pla files compute the same outputs");
+}
+
 fn main() -> Result<(), io::Error> {
-    let input = expr_input()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => {}
+        [flag, source] if flag == "--espresso" => {
+            match load_source(source).and_then(|system| Pla::from_system(&system)) {
+                Ok(pla) => print!("{}", pla.minimized()),
+                Err(err) => fail(err),
+            }
+            return Ok(());
+        }
+        [flag, source] if flag == "--analyse" => {
+            match load_source(source) {
+                Ok(system) => println!("{}", analysis(&system)),
+                Err(err) => fail(err),
+            }
+            return Ok(());
+        }
+        [flag, source] if flag == "--derive" => {
+            match load_source(source) {
+                Ok(system) => println!("{}", plain_text(&derivation_lines(&system))),
+                Err(err) => fail(err),
+            }
+            return Ok(());
+        }
+        // Exits with 1 when a step is wrong and 2 if the chain does not parse, like --equiv
+        [flag, chain] if flag == "--prove" => {
+            match parse_chain(chain, Dialect::Standard) {
+                Ok(exprs) => {
+                    let (valid, lines) = proof_lines(&exprs);
+                    println!("{}", plain_text(&lines));
+                    if !valid {
+                        std::process::exit(1);
+                    }
+                }
+                Err(err) => fail(err),
+            }
+            return Ok(());
+        }
+        [flag, source] if flag == "--cnf" || flag == "--tseitin" => {
+            match cnf_of(source, flag == "--cnf") {
+                Ok(text) => print!("{}", text),
+                Err(err) => fail(err),
+            }
+            return Ok(());
+        }
+        // Exits with 1 when the two differ and 2 on an error, for use in scripts
+        [flag, first, second] if flag == "--equiv" => {
+            let result = load_source(first)
+                .and_then(|first| load_source(second).map(|second| (first, second)))
+                .and_then(|(first, second)| equivalence(&first, &second));
+            match result {
+                Ok((equivalent, report)) => {
+                    println!("{}", report);
+                    if !equivalent {
+                        std::process::exit(1);
+                    }
+                }
+                Err(err) => fail(err),
+            }
+            return Ok(());
+        }
+        [path] if !path.starts_with('-') => {
+            match load_file(path) {
+                Ok(system) => show_tabs(path, system)?,
+                Err(err) => fail(err),
+            }
+            return Ok(());
+        }
+        [flag] if flag == "--help" || flag == "-h" => {
+            usage();
+            return Ok(());
+        }
+        _ => {
+            usage();
+            std::process::exit(2);
+        }
+    }
+
+    let (input, dialect) = expr_input()?;
     
     if input.trim().is_empty() {
         println!("No expression entered. Exiting.");
         return Ok(());
     }
     
-    // Validate the expression before proceeding
-    match std::panic::catch_unwind(|| parse_expr(input.trim())) {
-        Ok(_) => {
-            show_tabs(&input)?;
+    match parse_system(input.trim(), dialect) {
+        Ok(system) => {
+            show_tabs(&input, system)?;
             println!("Expression: {}", input);
         }
-        Err(_) => {
-            println!("Error: Invalid boolean expression format.");
+        Err(err) => {
+            println!("Error: Invalid boolean expression at {}", err);
             println!("Please use variables (A, B, C, etc.) and operators (&, |, !, ^)");
             println!("Example: A & B | !C");
+            std::process::exit(2);
         }
     }
     
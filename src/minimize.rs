@@ -0,0 +1,696 @@
+use crate::eval::Logic;
+use crate::espresso::{espresso, espresso_multi, output_covers, SharedTerm, MAX_CUBES, MAX_CUBE_VARIABLES};
+use crate::expr::{Definition, Expr, Output, System};
+use crate::truth_table::{output_values, output_variables, system_variables};
+use std::collections::{BTreeSet, HashSet};
+
+// Exact minimisation only runs up to this many inputs
+pub const MAX_EXACT_VARIABLES: usize = 12;
+
+// Petrick's method gives up on exactness past this many candidate covers
+const PETRICK_LIMIT: usize = 2000;
+
+// A product term. Bits set in `mask` are variables the term does not depend on;
+// the other bits of `value` give the required value of each variable. Bit
+// n-1 is the first variable, as in minterm numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Implicant {
+    pub value: usize,
+    pub mask: usize,
+}
+
+impl Implicant {
+    pub fn covers(&self, row: usize) -> bool {
+        row & !self.mask == self.value
+    }
+
+    pub fn literals(&self, n: usize) -> usize {
+        n - self.mask.count_ones() as usize
+    }
+
+    // Product of literals, e.g. `A & !C`
+    pub fn to_expr(self, vars: &[String]) -> Expr {
+        let n = vars.len();
+        let literals = vars.iter().enumerate().filter_map(|(i, v)| {
+            let bit = 1 << (n - 1 - i);
+            if self.mask & bit != 0 {
+                None
+            } else if self.value & bit != 0 {
+                Some(Expr::Var(v.clone()))
+            } else {
+                Some(Expr::Not(Box::new(Expr::Var(v.clone()))))
+            }
+        });
+        Expr::all(literals)
+    }
+
+    // Sum of the complemented literals, e.g. `!A | C`: the clause that is 0
+    // exactly where this term of the complement is 1
+    pub fn to_sum_expr(self, vars: &[String]) -> Expr {
+        let n = vars.len();
+        let literals = vars.iter().enumerate().filter_map(|(i, v)| {
+            let bit = 1 << (n - 1 - i);
+            if self.mask & bit != 0 {
+                None
+            } else if self.value & bit != 0 {
+                Some(Expr::Not(Box::new(Expr::Var(v.clone()))))
+            } else {
+                Some(Expr::Var(v.clone()))
+            }
+        });
+        Expr::any(literals)
+    }
+}
+
+// Quine-McCluskey: merge implicants that differ in one variable until nothing
+// merges any more. Whatever never merged is prime
+pub fn prime_implicants(on: &[usize], dont_cares: &[usize]) -> Vec<Implicant> {
+    let mut current: BTreeSet<Implicant> = on.iter().chain(dont_cares)
+        .map(|&row| Implicant { value: row, mask: 0 })
+        .collect();
+    let width = on.iter().chain(dont_cares).max().map_or(0, |&m| usize::BITS - m.leading_zeros());
+    let mut primes = Vec::new();
+
+    while !current.is_empty() {
+        let mut merged = HashSet::new();
+        let mut next = BTreeSet::new();
+
+        for term in &current {
+            for bit in (0..width).map(|b| 1usize << b) {
+                if term.mask & bit != 0 || term.value & bit != 0 {
+                    continue;
+                }
+                // Only look upwards so each pair is merged once
+                let partner = Implicant { value: term.value | bit, mask: term.mask };
+                if current.contains(&partner) {
+                    merged.insert(*term);
+                    merged.insert(partner);
+                    next.insert(Implicant { value: term.value, mask: term.mask | bit });
+                }
+            }
+        }
+
+        primes.extend(current.iter().filter(|t| !merged.contains(t)));
+        current = next;
+    }
+
+    // A prime made only of don't-cares is never needed
+    primes.retain(|p: &Implicant| on.iter().any(|&m| p.covers(m)));
+    primes
+}
+
+// Result of a two-level minimisation
+#[derive(Debug, Clone)]
+pub struct Minimized {
+    pub primes: Vec<Implicant>,
+    pub essential: Vec<Implicant>,
+    pub cover: Vec<Implicant>,
+    pub exact: bool,   // false if Petrick's method had to fall back to a greedy choice
+}
+
+// Minimal sum of products of the function that is 1 on `on`, free on
+// `dont_cares` and 0 everywhere else, over `n` variables
+pub fn minimize_sop(n: usize, on: &[usize], dont_cares: &[usize]) -> Minimized {
+    let primes = prime_implicants(on, dont_cares);
+
+    // Essential primes are the only cover of some minterm
+    let mut essential: Vec<Implicant> = Vec::new();
+    for &m in on {
+        let covering: Vec<&Implicant> = primes.iter().filter(|p| p.covers(m)).collect();
+        if covering.len() == 1 && !essential.contains(covering[0]) {
+            essential.push(*covering[0]);
+        }
+    }
+
+    let remaining: Vec<usize> = on.iter().copied()
+        .filter(|&m| !essential.iter().any(|p| p.covers(m)))
+        .collect();
+    let candidates: Vec<Implicant> = primes.iter().copied()
+        .filter(|p| !essential.contains(p))
+        .collect();
+
+    let (forced, core_primes, core_minterms) = reduce_table(n, &candidates, &remaining);
+    let (chosen, exact) = petrick(n, &core_primes, &core_minterms);
+
+    let mut cover = essential.clone();
+    cover.extend(forced);
+    cover.extend(chosen.iter().map(|&i| core_primes[i]));
+    cover.sort();
+
+    Minimized { primes, essential, cover, exact }
+}
+
+// Shrink the prime implicant chart before Petrick's method. Drops primes whose
+// minterms another prime of no greater cost also covers, and minterms whose
+// covering primes always cover some other minterm too, then picks up any prime
+// that has become the only cover of a minterm. Returns the primes picked up
+// and the cyclic core that is left
+fn reduce_table(
+    n: usize,
+    primes: &[Implicant],
+    minterms: &[usize],
+) -> (Vec<Implicant>, Vec<Implicant>, Vec<usize>) {
+    let mut primes = primes.to_vec();
+    let mut minterms = minterms.to_vec();
+    let mut forced = Vec::new();
+
+    loop {
+        let before = (primes.len(), minterms.len());
+
+        let covered = |p: &Implicant, minterms: &[usize]| -> Vec<usize> {
+            minterms.iter().copied().filter(|&m| p.covers(m)).collect()
+        };
+
+        // Column dominance
+        let columns: Vec<Vec<usize>> = primes.iter().map(|p| covered(p, &minterms)).collect();
+        let keep: Vec<bool> = (0..primes.len())
+            .map(|i| {
+                !columns[i].is_empty()
+                    && !(0..primes.len()).any(|j| {
+                        j != i
+                            && columns[i].iter().all(|m| columns[j].contains(m))
+                            && primes[j].literals(n) <= primes[i].literals(n)
+                            // Break ties between identical columns by position
+                            && (columns[i].len() < columns[j].len()
+                                || primes[j].literals(n) < primes[i].literals(n)
+                                || j < i)
+                    })
+            })
+            .collect();
+        primes = primes.iter().zip(&keep).filter(|&(_, &k)| k).map(|(&p, _)| p).collect();
+
+        // Row dominance
+        let rows: Vec<Vec<usize>> = minterms.iter()
+            .map(|&m| (0..primes.len()).filter(|&i| primes[i].covers(m)).collect())
+            .collect();
+        let keep: Vec<bool> = (0..minterms.len())
+            .map(|a| {
+                !(0..minterms.len()).any(|b| {
+                    b != a
+                        && rows[b].iter().all(|i| rows[a].contains(i))
+                        && (rows[b].len() < rows[a].len() || b < a)
+                })
+            })
+            .collect();
+        minterms = minterms.iter().zip(&keep).filter(|&(_, &k)| k).map(|(&m, _)| m).collect();
+
+        // Secondary essentials
+        for &m in &minterms {
+            let covering: Vec<Implicant> = primes.iter().copied().filter(|p| p.covers(m)).collect();
+            if covering.len() == 1 && !forced.contains(&covering[0]) {
+                forced.push(covering[0]);
+            }
+        }
+        minterms.retain(|&m| !forced.iter().any(|p| p.covers(m)));
+        primes.retain(|p| !forced.contains(p));
+
+        if (primes.len(), minterms.len()) == before {
+            return (forced, primes, minterms);
+        }
+    }
+}
+
+// Petrick's method: multiply out the product of "which primes cover this
+// minterm" sums and keep the cheapest product (fewest terms, then literals)
+fn petrick(n: usize, primes: &[Implicant], minterms: &[usize]) -> (Vec<usize>, bool) {
+    let greedy = greedy_cover(n, primes, minterms);
+
+    // Start with the minterms that have the fewest covers to keep the products small
+    let mut minterms = minterms.to_vec();
+    minterms.sort_by_key(|&m| primes.iter().filter(|p| p.covers(m)).count());
+
+    let mut products: Vec<BTreeSet<usize>> = vec![BTreeSet::new()];
+
+    for m in minterms {
+        let covering: Vec<usize> = (0..primes.len()).filter(|&i| primes[i].covers(m)).collect();
+
+        let mut next: Vec<BTreeSet<usize>> = Vec::new();
+        for product in &products {
+            if covering.iter().any(|i| product.contains(i)) {
+                next.push(product.clone());
+                continue;
+            }
+            // A product with more terms than the greedy cover can never win
+            if product.len() >= greedy.len() {
+                continue;
+            }
+            for &i in &covering {
+                let mut extended = product.clone();
+                extended.insert(i);
+                next.push(extended);
+            }
+        }
+
+        // Absorption: X + XY = X
+        next.sort_by_key(|p| p.len());
+        next.dedup();
+        let mut absorbed: Vec<BTreeSet<usize>> = Vec::new();
+        for product in next {
+            if !absorbed.iter().any(|kept| kept.is_subset(&product)) {
+                absorbed.push(product);
+            }
+        }
+        products = absorbed;
+
+        if products.len() > PETRICK_LIMIT {
+            return (greedy, false);
+        }
+    }
+
+    let cost = |p: &BTreeSet<usize>| (p.len(), p.iter().map(|&i| primes[i].literals(n)).sum::<usize>());
+    let greedy_set: BTreeSet<usize> = greedy.iter().copied().collect();
+    let best = products.into_iter().chain([greedy_set]).min_by_key(cost).unwrap_or_default();
+    (best.into_iter().collect(), true)
+}
+
+// Repeatedly take the prime that covers the most uncovered minterms
+fn greedy_cover(n: usize, primes: &[Implicant], minterms: &[usize]) -> Vec<usize> {
+    let mut uncovered: Vec<usize> = minterms.to_vec();
+    let mut chosen = Vec::new();
+    while !uncovered.is_empty() {
+        let best = (0..primes.len())
+            .max_by_key(|&i| {
+                let gain = uncovered.iter().filter(|&&m| primes[i].covers(m)).count();
+                (gain, usize::MAX - primes[i].literals(n))
+            })
+            .unwrap();
+        uncovered.retain(|&m| !primes[best].covers(m));
+        chosen.push(best);
+    }
+    chosen
+}
+
+// OR of the product terms, e.g. `A & !B | C`
+pub fn sop_expr(cover: &[Implicant], vars: &[String]) -> Expr {
+    Expr::any(cover.iter().map(|term| term.to_expr(vars)))
+}
+
+// AND of the sum terms that are 0 on each implicant of the complement,
+// e.g. `(A | C) & !B`
+pub fn pos_expr(cover: &[Implicant], vars: &[String]) -> Expr {
+    Expr::all(cover.iter().map(|term| term.to_sum_expr(vars)))
+}
+
+// Gates in a two-level circuit for the cover: one per term of two or more
+// literals, plus the gate combining the terms. Complemented inputs are
+// assumed to be available, so inverters are not counted
+pub fn gate_count(cover: &[Implicant], n: usize) -> usize {
+    let term_gates = cover.iter().filter(|term| term.literals(n) > 1).count();
+    term_gates + usize::from(cover.len() > 1)
+}
+
+// Row numbers of the 1s and of the don't-cares
+pub fn on_and_dont_care_rows(values: &[Logic]) -> (Vec<usize>, Vec<usize>) {
+    let rows = |wanted: Logic| (0..values.len()).filter(|&r| values[r] == wanted).collect();
+    (rows(Logic::One), rows(Logic::DontCare))
+}
+
+// Row numbers of the 0s
+pub fn off_rows(values: &[Logic]) -> Vec<usize> {
+    (0..values.len()).filter(|&r| values[r] == Logic::Zero).collect()
+}
+
+// Both minimal two-level forms of one output
+pub struct OutputForms {
+    pub vars: Vec<String>,
+    pub sop: Minimized,  // covers the 1s
+    pub pos: Minimized,  // covers the 0s
+    pub heuristic: bool, // found by `espresso`, with no prime implicant lists
+}
+
+impl OutputForms {
+    pub fn sop_expr(&self) -> Expr {
+        sop_expr(&self.sop.cover, &self.vars)
+    }
+
+    pub fn pos_expr(&self) -> Expr {
+        pos_expr(&self.pos.cover, &self.vars)
+    }
+}
+
+// Minimal SOP and POS of an output: exact up to MAX_EXACT_VARIABLES inputs,
+// heuristic past that, and None past what a cube can hold or once the cube
+// lists grow past MAX_CUBES
+pub fn minimize_output(system: &System, output: &Output) -> Option<OutputForms> {
+    let vars = output_variables(system, output);
+    if vars.len() > MAX_CUBE_VARIABLES {
+        return None;
+    }
+    if vars.len() > MAX_EXACT_VARIABLES {
+        return espresso_output(system, output, vars);
+    }
+
+    let values = output_values(system, output, &vars);
+    let (on, dont_cares) = on_and_dont_care_rows(&values);
+    let off = off_rows(&values);
+    let sop = minimize_sop(vars.len(), &on, &dont_cares);
+    let pos = minimize_sop(vars.len(), &off, &dont_cares);
+
+    Some(OutputForms { vars, sop, pos, heuristic: false })
+}
+
+// Both forms from cube lists, without going through the truth table
+fn espresso_output(system: &System, output: &Output, vars: Vec<String>) -> Option<OutputForms> {
+    let n = vars.len();
+    let dont_care = output.dont_care.as_ref().map(|dont_care| system.inline(dont_care));
+    let [on, dont_cares, off] = output_covers(&system.inline(&output.expr), dont_care.as_ref(), &vars)?;
+
+    let heuristic = |cover: Vec<Implicant>| Minimized { primes: Vec::new(), essential: Vec::new(), cover, exact: false };
+    let sop = heuristic(espresso(n, &on, &dont_cares));
+    let pos = heuristic(espresso(n, &off, &dont_cares));
+
+    Some(OutputForms { vars, sop, pos, heuristic: true })
+}
+
+// One set of product terms for all outputs of a system, over all its inputs
+pub struct SharedCover {
+    pub vars: Vec<String>,
+    pub terms: Vec<SharedTerm>,
+}
+
+impl SharedCover {
+    // AND plane: a column per input literal, a row per term
+    pub fn and_plane(&self) -> (usize, usize, usize) {
+        let n = self.vars.len();
+        let connections = self.terms.iter().map(|t| t.cube.literals(n)).sum();
+        (2 * n, self.terms.len(), connections)
+    }
+
+    // OR plane: a row per term, a column per output
+    pub fn or_plane(&self, outputs: usize) -> (usize, usize, usize) {
+        let connections = self.terms.iter().map(|t| t.outputs.len()).sum();
+        (self.terms.len(), outputs, connections)
+    }
+}
+
+// Shared minimisation of every output at once, None past what a cube can hold
+// or once the cube lists grow past MAX_CUBES
+pub fn shared_cover(system: &System) -> Option<SharedCover> {
+    let vars = system_variables(system);
+    if vars.len() > MAX_CUBE_VARIABLES {
+        return None;
+    }
+    let mut on = Vec::new();
+    let mut dont_cares = Vec::new();
+    for output in &system.outputs {
+        let dont_care = output.dont_care.as_ref().map(|dont_care| system.inline(dont_care));
+        let [output_on, output_dont_cares, _] = output_covers(&system.inline(&output.expr), dont_care.as_ref(), &vars)?;
+        on.push(output_on);
+        dont_cares.push(output_dont_cares);
+    }
+    let terms = espresso_multi(vars.len(), &on, &dont_cares);
+    Some(SharedCover { vars, terms })
+}
+
+// Which two-level form to build a circuit from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Form {
+    Sop,
+    Pos,
+    Shared,
+}
+
+// Shared terms become `let` wires P1, P2... so the circuit draws each once and
+// fans it out to every output that uses it
+fn shared_system(system: &System, cover: SharedCover) -> System {
+    let taken: HashSet<&str> = cover.vars.iter().map(String::as_str)
+        .chain(system.outputs.iter().map(|o| o.name.as_str()))
+        .collect();
+    let prefix = ["P", "T", "Term"].into_iter()
+        .find(|prefix| (1..=cover.terms.len()).all(|i| !taken.contains(format!("{}{}", prefix, i).as_str())))
+        .unwrap_or("P_");
+
+    let names: Vec<String> = (1..=cover.terms.len()).map(|i| format!("{}{}", prefix, i)).collect();
+    let definitions = cover.terms.iter().zip(&names)
+        .map(|(term, name)| Definition { name: name.clone(), expr: term.cube.to_expr(&cover.vars) })
+        .collect();
+    let outputs = system.outputs.iter().enumerate()
+        .map(|(o, output)| Output {
+            name: output.name.clone(),
+            expr: Expr::any(cover.terms.iter().zip(&names)
+                .filter(|(term, _)| term.outputs.contains(&o))
+                .map(|(_, name)| Expr::Ref(name.clone()))),
+            dont_care: None,
+        })
+        .collect();
+
+    System { inputs: system.inputs.clone(), definitions, outputs }
+}
+
+// The system with every output replaced by its minimal form. Outputs too
+// large to minimise keep their original expression, with the wires inlined
+pub fn minimized_system(system: &System, form: Form) -> System {
+    if form == Form::Shared {
+        return match shared_cover(system) {
+            Some(cover) => shared_system(system, cover),
+            None => minimized_system(system, Form::Sop),
+        };
+    }
+    let outputs = system.outputs.iter()
+        .map(|output| {
+            let expr = match (minimize_output(system, output), form) {
+                (Some(forms), Form::Sop) => forms.sop_expr(),
+                (Some(forms), _) => forms.pos_expr(),
+                (None, _) => system.inline(&output.expr),
+            };
+            Output { name: output.name.clone(), expr, dont_care: None }
+        })
+        .collect();
+
+    System { inputs: system.inputs.clone(), definitions: Vec::new(), outputs }
+}
+
+// Number of variable occurrences in an expression
+pub fn literal_count(expr: &Expr) -> usize {
+    match expr {
+        Expr::Var(_) | Expr::Ref(_) => 1,
+        Expr::Const(_) => 0,
+        Expr::Not(inner) => literal_count(inner),
+        Expr::And(a, b) | Expr::Or(a, b) | Expr::Xor(a, b) | Expr::Xnor(a, b)
+        | Expr::Nand(a, b) | Expr::Nor(a, b) | Expr::Implies(a, b) | Expr::Iff(a, b) => {
+            literal_count(a) + literal_count(b)
+        }
+    }
+}
+
+// Number of top-level OR terms
+pub fn term_count(expr: &Expr) -> usize {
+    match expr {
+        Expr::Or(a, b) => term_count(a) + term_count(b),
+        Expr::Const(false) => 0,
+        _ => 1,
+    }
+}
+
+// One line of the Simplified tab for a minimal form
+fn form_line(label: &str, expr: &Expr, forms: &OutputForms, result: &Minimized, cheaper: bool) -> String {
+    let note = if forms.heuristic {
+        " (Espresso heuristic, may not be minimal)"
+    } else if !result.exact {
+        " (cover chosen greedily, may not be minimal)"
+    } else {
+        ""
+    };
+    format!(
+        "  {}  {}    [{} terms, {} literals, {} gates]{}{}",
+        label, expr, result.cover.len(), literal_count(expr), gate_count(&result.cover, forms.vars.len()),
+        if cheaper { " <- cheaper" } else { "" },
+        note
+    )
+}
+
+// Text for the Simplified tab: every output next to its minimal SOP and POS
+pub fn simplification(system: &System) -> String {
+    let mut lines = Vec::new();
+
+    for output in &system.outputs {
+        let vars = output_variables(system, output);
+        lines.push(format!("{} ({})", output.name, vars.join(", ")));
+
+        let original = system.inline(&output.expr);
+        lines.push(format!(
+            "  Original     {}    [{} terms, {} literals]",
+            output.expr, term_count(&original), literal_count(&original)
+        ));
+
+        let Some(forms) = minimize_output(system, output) else {
+            lines.push(format!(
+                "  Minimal SOP  too large to minimise (at most {} inputs and {} product terms)",
+                MAX_CUBE_VARIABLES, MAX_CUBES
+            ));
+            lines.push(String::new());
+            continue;
+        };
+
+        let n = vars.len();
+        let sop = forms.sop_expr();
+        let pos = forms.pos_expr();
+        let sop_cost = (literal_count(&sop), gate_count(&forms.sop.cover, n));
+        let pos_cost = (literal_count(&pos), gate_count(&forms.pos.cover, n));
+        lines.push(form_line("Minimal SOP", &sop, &forms, &forms.sop, sop_cost < pos_cost));
+        lines.push(form_line("Minimal POS", &pos, &forms, &forms.pos, pos_cost < sop_cost));
+        if forms.heuristic {
+            lines.push(String::new());
+            continue;
+        }
+
+        let primes: Vec<String> = forms.sop.primes.iter()
+            .map(|p| {
+                let marker = if forms.sop.essential.contains(p) { "*" } else { "" };
+                format!("{}{}", p.to_expr(&vars), marker)
+            })
+            .collect();
+        lines.push(format!("  Prime implicants ({}, * = essential): {}", primes.len(), primes.join(", ")));
+
+        let implicates: Vec<String> = forms.pos.primes.iter()
+            .map(|p| {
+                let marker = if forms.pos.essential.contains(p) { "*" } else { "" };
+                format!("{}{}", p.to_sum_expr(&vars), marker)
+            })
+            .collect();
+        lines.push(format!("  Prime implicates ({}, * = essential): {}", implicates.len(), implicates.join(", ")));
+        lines.push(String::new());
+    }
+
+    if system.outputs.len() > 1 {
+        lines.extend(shared_section(system));
+    }
+
+    lines.join("\n")
+}
+
+// The multi-output cover as a PLA: plane sizes, then a row per term marking
+// the outputs it feeds
+fn shared_section(system: &System) -> Vec<String> {
+    let Some(cover) = shared_cover(system) else {
+        return vec![format!(
+            "Shared PLA  too large to minimise (at most {} inputs and {} product terms)",
+            MAX_CUBE_VARIABLES, MAX_CUBES
+        )];
+    };
+    let m = system.outputs.len();
+    let (and_inputs, and_terms, and_connections) = cover.and_plane();
+    let (or_terms, or_outputs, or_connections) = cover.or_plane(m);
+    let separate: usize = system.outputs.iter()
+        .filter_map(|output| minimize_output(system, output))
+        .map(|forms| forms.sop.cover.len())
+        .sum();
+
+    let mut lines = vec![
+        format!("Shared PLA ({}) (Espresso heuristic, may not be minimal)", cover.vars.join(", ")),
+        format!("  AND plane  {} inputs x {} terms, {} connections", and_inputs, and_terms, and_connections),
+        format!("  OR plane   {} terms x {} outputs, {} connections", or_terms, or_outputs, or_connections),
+        format!("  {} terms, against {} when each output is minimised alone", cover.terms.len(), separate),
+        String::new(),
+    ];
+
+    let terms: Vec<String> = cover.terms.iter().map(|t| t.cube.to_expr(&cover.vars).to_string()).collect();
+    let width = terms.iter().map(|t| t.chars().count()).max().unwrap_or(0).max("Term".len());
+    let header: Vec<String> = system.outputs.iter().map(|o| o.name.clone()).collect();
+    lines.push(format!("  {:<width$}  {}", "Term", header.join("  "), width = width));
+    for (term, text) in cover.terms.iter().zip(&terms) {
+        let marks: Vec<String> = system.outputs.iter().enumerate()
+            .map(|(o, output)| {
+                let mark = if term.outputs.contains(&o) { "x" } else { "." };
+                format!("{:^w$}", mark, w = output.name.chars().count())
+            })
+            .collect();
+        let line = format!("  {:<width$}  {}", text, marks.join("  "), width = width);
+        lines.push(line.trim_end().to_string());
+    }
+    lines.push(String::new());
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::random;
+
+    // A random function of `n` inputs: on-set, don't-cares and off-set rows
+    fn random_function(seed: &mut u64, n: usize) -> (Vec<usize>, Vec<usize>, Vec<usize>) {
+        let (mut on, mut dont_cares, mut off) = (Vec::new(), Vec::new(), Vec::new());
+        for row in 0..1 << n {
+            match random(seed, 5) {
+                0 | 1 => on.push(row),
+                2 => dont_cares.push(row),
+                _ => off.push(row),
+            }
+        }
+        (on, dont_cares, off)
+    }
+
+    fn cost(cover: &[Implicant], n: usize) -> (usize, usize) {
+        (cover.len(), cover.iter().map(|p| p.literals(n)).sum())
+    }
+
+    #[test]
+    fn covers_the_on_set_and_misses_the_off_set() {
+        let mut seed = 0x2545f4914f6cdd1d;
+        for _ in 0..300 {
+            let n = 1 + random(&mut seed, 6);
+            let (on, dont_cares, off) = random_function(&mut seed, n);
+            let result = minimize_sop(n, &on, &dont_cares);
+            for &row in &on {
+                assert!(result.cover.iter().any(|p| p.covers(row)), "{:?} {:?}", on, dont_cares);
+            }
+            for &row in &off {
+                assert!(!result.cover.iter().any(|p| p.covers(row)), "{:?} {:?}", on, dont_cares);
+            }
+            for essential in &result.essential {
+                assert!(result.cover.contains(essential));
+            }
+        }
+    }
+
+    #[test]
+    fn primes_are_maximal_implicants() {
+        let mut seed = 0x51afd6c9b1e2f3a7;
+        for _ in 0..200 {
+            let n = 1 + random(&mut seed, 5);
+            let (on, dont_cares, off) = random_function(&mut seed, n);
+            for prime in prime_implicants(&on, &dont_cares) {
+                assert!(!off.iter().any(|&row| prime.covers(row)));
+                for bit in (0..n).map(|b| 1 << b).filter(|bit| prime.mask & bit == 0) {
+                    let grown = Implicant { value: prime.value & !bit, mask: prime.mask | bit };
+                    assert!(off.iter().any(|&row| grown.covers(row)), "{:?} is not prime", prime);
+                }
+            }
+        }
+    }
+
+    // Against every subset of the primes, on functions small enough to try
+    #[test]
+    fn exact_covers_are_minimal() {
+        let mut seed = 0x94d049bb133111eb;
+        for _ in 0..200 {
+            let n = 1 + random(&mut seed, 4);
+            let (on, dont_cares, _) = random_function(&mut seed, n);
+            let result = minimize_sop(n, &on, &dont_cares);
+            let primes = &result.primes;
+            if !result.exact || primes.len() > 16 {
+                continue;
+            }
+            let best = (0..1usize << primes.len())
+                .map(|subset| (0..primes.len()).filter(|i| subset >> i & 1 == 1).map(|i| primes[i]).collect::<Vec<_>>())
+                .filter(|cover| on.iter().all(|&row| cover.iter().any(|p| p.covers(row))))
+                .map(|cover| cost(&cover, n))
+                .min()
+                .unwrap();
+            assert_eq!(cost(&result.cover, n), best, "{:?} {:?}", on, dont_cares);
+        }
+    }
+
+    // An XOR of n inputs needs 2^(n-1) product terms, so past 10 inputs
+    // minimisation has to give up rather than run for minutes
+    #[test]
+    fn gives_up_past_the_cube_budget() {
+        use crate::parser::{parse_system, Dialect};
+        for (n, fits) in [(10, true), (14, false), (24, false)] {
+            let names: Vec<String> = (0..n).map(|i| format!("V{}", i)).collect();
+            let system = parse_system(&format!("F = {}", names.join(" ^ ")), Dialect::Standard).unwrap();
+            assert_eq!(minimize_output(&system, &system.outputs[0]).is_some(), fits, "{} inputs", n);
+            assert_eq!(shared_cover(&system).is_some(), fits, "{} inputs", n);
+        }
+    }
+}
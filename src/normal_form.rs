@@ -0,0 +1,437 @@
+use crate::eval::Logic;
+use crate::expr::{Expr, Output, System};
+use crate::minimize::{literal_count, pos_expr, sop_expr, term_count, Implicant};
+use crate::truth_table::{output_values, output_variables, MAX_TABLE_VARIABLES};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Wrap},
+    Frame,
+};
+
+// Results with more terms than this are not built
+const MAX_TERMS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalForm {
+    Nnf,
+    Cnf,
+    Dnf,
+    CanonicalSop,
+    CanonicalPos,
+    Anf,
+}
+
+impl NormalForm {
+    pub const ALL: [NormalForm; 6] = [
+        NormalForm::Nnf, NormalForm::Cnf, NormalForm::Dnf,
+        NormalForm::CanonicalSop, NormalForm::CanonicalPos, NormalForm::Anf,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NormalForm::Nnf => "NNF",
+            NormalForm::Cnf => "CNF",
+            NormalForm::Dnf => "DNF",
+            NormalForm::CanonicalSop => "Canonical SOP",
+            NormalForm::CanonicalPos => "Canonical POS",
+            NormalForm::Anf => "ANF",
+        }
+    }
+}
+
+// An output in one of the normal forms, with its wires inlined. The canonical
+// forms and ANF come from the truth table and leave out the don't-cares; the
+// others are rewrites of the expression and ignore them
+pub fn normal_form(system: &System, output: &Output, form: NormalForm) -> Result<Expr, String> {
+    let expr = system.inline(&output.expr);
+    let vars = output_variables(system, output);
+    let rewritten = matches!(form, NormalForm::Nnf | NormalForm::Cnf | NormalForm::Dnf);
+    if rewritten && nnf_size(&expr) > MAX_TERMS * 16 {
+        return Err(format!("more than {} literals", MAX_TERMS * 16));
+    }
+    match form {
+        NormalForm::Nnf => Ok(nnf(&expr, false)),
+        NormalForm::Cnf | NormalForm::Dnf => {
+            let cnf = form == NormalForm::Cnf;
+            let sets = distribute(&nnf(&expr, false), &vars, cnf)
+                .ok_or_else(|| format!("more than {} {}", MAX_TERMS, if cnf { "clauses" } else { "terms" }))?;
+            let literal = |&(var, positive): &(usize, bool)| {
+                let var = Expr::Var(vars[var].clone());
+                if positive { var } else { Expr::Not(Box::new(var)) }
+            };
+            Ok(if cnf {
+                Expr::all(sets.iter().map(|clause| Expr::any(clause.iter().map(literal))))
+            } else {
+                Expr::any(sets.iter().map(|term| Expr::all(term.iter().map(literal))))
+            })
+        }
+        NormalForm::CanonicalSop | NormalForm::CanonicalPos | NormalForm::Anf => {
+            if vars.len() > MAX_TABLE_VARIABLES {
+                return Err(format!("more than {} inputs", MAX_TABLE_VARIABLES));
+            }
+            let values = output_values(system, output, &vars);
+            let rows = |wanted: Logic| -> Vec<Implicant> {
+                (0..values.len())
+                    .filter(|&row| values[row] == wanted)
+                    .map(|row| Implicant { value: row, mask: 0 })
+                    .collect()
+            };
+            let terms = match form {
+                NormalForm::CanonicalSop => rows(Logic::One),
+                NormalForm::CanonicalPos => rows(Logic::Zero),
+                _ => anf_monomials(&values),
+            };
+            if terms.len() > MAX_TERMS {
+                return Err(format!("more than {} terms", MAX_TERMS));
+            }
+            Ok(match form {
+                NormalForm::CanonicalSop => sop_expr(&terms, &vars),
+                NormalForm::CanonicalPos => pos_expr(&terms, &vars),
+                _ => anf_expr(&terms, &vars),
+            })
+        }
+    }
+}
+
+// Literals and constants in the NNF of an expression, which is the same for
+// its negation. XOR and equivalence copy both operands, so this can grow
+// exponentially, even with nothing but constants
+fn nnf_size(expr: &Expr) -> usize {
+    match expr {
+        Expr::Var(_) | Expr::Ref(_) | Expr::Const(_) => 1,
+        Expr::Not(inner) => nnf_size(inner),
+        Expr::And(a, b) | Expr::Or(a, b) | Expr::Nand(a, b) | Expr::Nor(a, b) | Expr::Implies(a, b) => {
+            nnf_size(a).saturating_add(nnf_size(b))
+        }
+        Expr::Xor(a, b) | Expr::Xnor(a, b) | Expr::Iff(a, b) => {
+            nnf_size(a).saturating_add(nnf_size(b)).saturating_mul(2)
+        }
+    }
+}
+
+// Negation normal form: only AND, OR and NOT, with every NOT on a variable.
+// `negate` builds the NNF of the complement instead
+pub fn nnf(expr: &Expr, negate: bool) -> Expr {
+    let and = |a: Expr, b: Expr| Expr::And(Box::new(a), Box::new(b));
+    let or = |a: Expr, b: Expr| Expr::Or(Box::new(a), Box::new(b));
+    // AND when the result is not negated, OR when it is (De Morgan)
+    let join = |negate: bool, a: Expr, b: Expr| if negate { or(a, b) } else { and(a, b) };
+    let meet = |negate: bool, a: Expr, b: Expr| if negate { and(a, b) } else { or(a, b) };
+
+    match expr {
+        Expr::Var(_) | Expr::Ref(_) => {
+            if negate { Expr::Not(Box::new(expr.clone())) } else { expr.clone() }
+        }
+        Expr::Const(value) => Expr::Const(*value != negate),
+        Expr::Not(inner) => nnf(inner, !negate),
+        Expr::And(a, b) => join(negate, nnf(a, negate), nnf(b, negate)),
+        Expr::Nand(a, b) => join(!negate, nnf(a, !negate), nnf(b, !negate)),
+        Expr::Or(a, b) => meet(negate, nnf(a, negate), nnf(b, negate)),
+        Expr::Nor(a, b) => meet(!negate, nnf(a, !negate), nnf(b, !negate)),
+        Expr::Implies(a, b) => meet(negate, nnf(a, !negate), nnf(b, negate)),
+        Expr::Xor(a, b) | Expr::Xnor(a, b) | Expr::Iff(a, b) => {
+            // a ^ b = a & !b | !a & b, and its complement a & b | !a & !b
+            let odd = matches!(expr, Expr::Xor(..)) != negate;
+            or(
+                and(nnf(a, false), nnf(b, odd)),
+                and(nnf(a, true), nnf(b, !odd)),
+            )
+        }
+    }
+}
+
+// A clause (for CNF) or term (for DNF): variable indices and polarities, sorted
+type LiteralSet = Vec<(usize, bool)>;
+
+// CNF or DNF of an expression in NNF as sets of literals, by distributing OR
+// over AND (or AND over OR). Always-true clauses, or always-false terms, are
+// dropped, as is any set containing another. None once there are too many
+fn distribute(expr: &Expr, vars: &[String], cnf: bool) -> Option<Vec<LiteralSet>> {
+    match expr {
+        Expr::Var(name) => Some(vec![vec![(vars.iter().position(|v| v == name)?, true)]]),
+        Expr::Not(inner) => {
+            let Expr::Var(name) = inner.as_ref() else { unreachable!("not in NNF") };
+            Some(vec![vec![(vars.iter().position(|v| v == name)?, false)]])
+        }
+        // In CNF true is no clauses and false one empty clause; DNF the other way round
+        Expr::Const(value) => Some(if *value == cnf { Vec::new() } else { vec![Vec::new()] }),
+        Expr::And(a, b) | Expr::Or(a, b) => {
+            let (a, b) = (distribute(a, vars, cnf)?, distribute(b, vars, cnf)?);
+            // AND in CNF, or OR in DNF, just collects the sets of both sides
+            let sets = if matches!(expr, Expr::And(..)) == cnf {
+                a.into_iter().chain(b).collect()
+            } else {
+                if a.len().saturating_mul(b.len()) > MAX_TERMS * 4 {
+                    return None;
+                }
+                a.iter()
+                    .flat_map(|x| b.iter().map(move |y| x.iter().chain(y).copied().collect()))
+                    .collect()
+            };
+            let sets = simplify(sets);
+            (sets.len() <= MAX_TERMS).then_some(sets)
+        }
+        _ => unreachable!("not in NNF"),
+    }
+}
+
+fn simplify(sets: Vec<LiteralSet>) -> Vec<LiteralSet> {
+    let mut sets: Vec<LiteralSet> = sets.into_iter()
+        .map(|mut set| {
+            set.sort();
+            set.dedup();
+            set
+        })
+        .filter(|set| !set.windows(2).any(|w| w[0].0 == w[1].0))
+        .collect();
+    sets.sort_by_key(|set| set.len());
+    sets.dedup();
+
+    let mut kept: Vec<LiteralSet> = Vec::new();
+    for set in sets {
+        if !kept.iter().any(|k| k.iter().all(|literal| set.contains(literal))) {
+            kept.push(set);
+        }
+    }
+    kept
+}
+
+// Coefficients of the Reed-Muller expansion by the Moebius transform, as
+// the monomials whose coefficient is 1: each is an Implicant whose value has
+// a bit set for every variable in it. Don't-cares count as 0
+fn anf_monomials(values: &[Logic]) -> Vec<Implicant> {
+    let mut coefficients: Vec<bool> = values.iter().map(|v| *v == Logic::One).collect();
+    let mut step = 1;
+    while step < coefficients.len() {
+        for row in 0..coefficients.len() {
+            if row & step != 0 {
+                coefficients[row] ^= coefficients[row ^ step];
+            }
+        }
+        step <<= 1;
+    }
+    // Constant first, then by degree, with A & B before A & C
+    let mut rows: Vec<usize> = (0..coefficients.len()).filter(|&row| coefficients[row]).collect();
+    rows.sort_by_key(|&row| (row.count_ones(), std::cmp::Reverse(row)));
+    rows.into_iter().map(|row| Implicant { value: row, mask: 0 }).collect()
+}
+
+// XOR of the monomials, 1 for the empty one
+fn anf_expr(monomials: &[Implicant], vars: &[String]) -> Expr {
+    let n = vars.len();
+    monomials.iter()
+        .map(|monomial| {
+            Expr::all((0..n)
+                .filter(|i| monomial.value & (1 << (n - 1 - i)) != 0)
+                .map(|i| Expr::Var(vars[i].clone())))
+        })
+        .reduce(|a, b| Expr::Xor(Box::new(a), Box::new(b)))
+        .unwrap_or(Expr::Const(false))
+}
+
+// Top-level clauses of a product of sums, monomials of an ANF, or terms
+fn size_text(expr: &Expr, form: NormalForm) -> String {
+    fn operands(expr: &Expr, same: &dyn Fn(&Expr) -> bool) -> usize {
+        match expr {
+            Expr::And(a, b) | Expr::Xor(a, b) if same(expr) => operands(a, same) + operands(b, same),
+            Expr::Const(_) => 0,
+            _ => 1,
+        }
+    }
+    match form {
+        NormalForm::Cnf | NormalForm::CanonicalPos => {
+            format!("{} clauses", operands(expr, &|e| matches!(e, Expr::And(..))))
+        }
+        NormalForm::Anf => {
+            let count = match expr {
+                Expr::Const(true) => 1,
+                _ => operands(expr, &|e| matches!(e, Expr::Xor(..))),
+            };
+            format!("{} monomials", count)
+        }
+        _ => format!("{} terms", term_count(expr)),
+    }
+}
+
+// The Normal Forms tab: every form of every output, one selected at a time
+pub struct NormalFormsView {
+    entries: Vec<(String, NormalForm, Result<Expr, String>)>,
+    selected: usize,
+}
+
+impl NormalFormsView {
+    pub fn new(system: &System) -> Self {
+        let entries = system.outputs.iter()
+            .flat_map(|output| {
+                NormalForm::ALL.map(|form| (output.name.clone(), form, normal_form(system, output, form)))
+            })
+            .collect();
+        Self { entries, selected: 0 }
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    // The selected form and a name for it, unless it could not be built
+    pub fn selected_expr(&self) -> Option<(String, Expr)> {
+        let (name, form, result) = &self.entries[self.selected];
+        let expr = result.as_ref().ok()?;
+        Some((format!("{} of {}", form.name(), name), expr.clone()))
+    }
+
+    pub fn render(&self, f: &mut Frame, area: Rect) {
+        let width = area.width.saturating_sub(2).max(1) as usize;
+        let mut lines = Vec::new();
+        let mut selected_line = 0;
+        for (i, (name, form, result)) in self.entries.iter().enumerate() {
+            if i == self.selected {
+                selected_line = lines.iter().map(|line: &Line| line.width().div_ceil(width).max(1)).sum();
+            }
+            let heading = match result {
+                Ok(expr) => format!(
+                    "{} {}  [{}, {} literals]",
+                    name, form.name(), size_text(expr, *form), literal_count(expr)
+                ),
+                Err(_) => format!("{} {}", name, form.name()),
+            };
+            let style = if i == self.selected {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Cyan)
+            };
+            lines.push(Line::styled(heading, style));
+            lines.push(Line::from(match result {
+                Ok(expr) => format!("  {}", expr),
+                Err(reason) => format!("  not built: {}", reason),
+            }));
+            lines.push(Line::from(""));
+        }
+
+        // Keep the selected form near the top once it would scroll off
+        let height = area.height.saturating_sub(2) as usize;
+        let scroll = selected_line.saturating_sub(height / 3);
+        let content = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL)
+                .title("Normal Forms (Up/Down to pick, Enter to draw the circuit)"))
+            .wrap(Wrap { trim: false })
+            .scroll((scroll as u16, 0));
+        f.render_widget(content, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::eval;
+    use crate::parser::{parse_system, Dialect};
+    use std::collections::HashMap;
+
+    // Values of an expression on every row, first variable as the MSB
+    fn values(expr: &Expr, vars: &[String]) -> Vec<bool> {
+        let n = vars.len();
+        (0..1usize << n)
+            .map(|row| {
+                let assignment: HashMap<String, bool> = vars.iter().enumerate()
+                    .map(|(i, v)| (v.clone(), row >> (n - 1 - i) & 1 == 1))
+                    .collect();
+                eval(expr, &assignment)
+            })
+            .collect()
+    }
+
+    fn is_literal(expr: &Expr) -> bool {
+        match expr {
+            Expr::Var(_) => true,
+            Expr::Not(inner) => matches!(inner.as_ref(), Expr::Var(_)),
+            _ => false,
+        }
+    }
+
+    fn is_nnf(expr: &Expr) -> bool {
+        match expr {
+            Expr::And(a, b) | Expr::Or(a, b) => is_nnf(a) && is_nnf(b),
+            Expr::Const(_) => true,
+            _ => is_literal(expr),
+        }
+    }
+
+    // An `outer` of `inner`s of literals, e.g. an AND of ORs for CNF
+    fn is_two_level(expr: &Expr, and_outside: bool) -> bool {
+        fn level(expr: &Expr, and: bool, inside: &dyn Fn(&Expr) -> bool) -> bool {
+            match expr {
+                Expr::And(a, b) if and => level(a, and, inside) && level(b, and, inside),
+                Expr::Or(a, b) if !and => level(a, and, inside) && level(b, and, inside),
+                _ => inside(expr),
+            }
+        }
+        let inner = |e: &Expr| matches!(e, Expr::Const(_)) || level(e, !and_outside, &is_literal);
+        level(expr, and_outside, &inner)
+    }
+
+    // The rewrites keep the expression's value on every row, don't-cares
+    // included. The canonical forms and ANF take the don't-cares as 0, except
+    // the product of sums, which leaves them out of its clauses
+    #[test]
+    fn forms_keep_the_truth_table() {
+        let sources = [
+            "F = A & B | !C",
+            "F = (A ^ B) & (A ^ B ^ C) | !(A -> D)",
+            "let w = A !& B; F = w <-> (C !| A); G = w ^ 1",
+            "F = A ^ B ^ C; dc F = A & B",
+            "F = A & 0 | B & 1",
+            "F = 1",
+            "F = A & !A",
+            "F = A !| (B -> C); dc F = !A",
+        ];
+        for source in sources {
+            let system = parse_system(source, Dialect::Standard).unwrap();
+            for output in &system.outputs {
+                let vars = output_variables(&system, output);
+                let expected = values(&system.inline(&output.expr), &vars);
+                let logic = output_values(&system, output, &vars);
+                for form in NormalForm::ALL {
+                    let expr = normal_form(&system, output, form).unwrap();
+                    let found = values(&expr, &vars);
+                    let context = format!("{} {} {}: {}", source, output.name, form.name(), expr);
+                    match form {
+                        NormalForm::Nnf | NormalForm::Cnf | NormalForm::Dnf => assert_eq!(found, expected, "{}", context),
+                        NormalForm::CanonicalPos => {
+                            let zeros: Vec<bool> = logic.iter().map(|v| *v != Logic::Zero).collect();
+                            assert_eq!(found, zeros, "{}", context);
+                        }
+                        _ => {
+                            let ones: Vec<bool> = logic.iter().map(|v| *v == Logic::One).collect();
+                            assert_eq!(found, ones, "{}", context);
+                        }
+                    }
+                    match form {
+                        NormalForm::Nnf => assert!(is_nnf(&expr), "{}", context),
+                        NormalForm::Cnf | NormalForm::CanonicalPos => assert!(is_two_level(&expr, true), "{}", context),
+                        NormalForm::Dnf | NormalForm::CanonicalSop => assert!(is_two_level(&expr, false), "{}", context),
+                        NormalForm::Anf => {}
+                    }
+                }
+            }
+        }
+    }
+
+    // Each XOR doubles the NNF, constants or not, so this has to be turned
+    // down rather than built
+    #[test]
+    fn long_constant_xor_is_refused() {
+        let source = format!("F = {}", vec!["1"; 40].join(" ^ "));
+        let system = parse_system(&source, Dialect::Standard).unwrap();
+        for form in [NormalForm::Nnf, NormalForm::Cnf, NormalForm::Dnf] {
+            assert!(normal_form(&system, &system.outputs[0], form).is_err(), "{}", form.name());
+        }
+    }
+}
@@ -1,68 +1,550 @@
 use pest::Parser;
+use pest::error::{ErrorVariant, InputLocation, LineColLocation};
 use pest_derive::Parser;
+use std::fmt;
 
-use crate::expr::Expr;
+use crate::expr::{Definition, Expr, Output, System};
+use crate::truth_table::variables;
 
 #[derive(Parser)]
 #[grammar = "boolean.pest"]
 pub struct BooleanParser;
 
-pub fn parse_expr(input: &str) -> Expr {
-    let pairs = BooleanParser::parse(Rule::expr, input)
-        .expect("Parse Error")
-        .next()
-        .unwrap();
+// A parse failure with enough position information to point at the culprit
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub offset: usize,        // byte offset into the input
+    pub line: usize,          // 1-based
+    pub column: usize,        // 1-based, in characters
+    pub expected: Vec<String>,
+    pub reason: Option<String>,  // set when the grammar matched but the meaning is invalid
+    pub snippet: String,      // offending line with a caret underline
+}
+
+impl ParseError {
+    fn from_pest(err: pest::error::Error<Rule>, input: &str) -> Self {
+        let (offset, end) = match err.location {
+            InputLocation::Pos(pos) => (pos, pos),
+            InputLocation::Span((start, end)) => (start, end),
+        };
+        let (line, column) = match err.line_col {
+            LineColLocation::Pos(pos) => pos,
+            LineColLocation::Span(start, _) => start,
+        };
+
+        let (expected, reason) = match &err.variant {
+            ErrorVariant::ParsingError { positives, .. } => {
+                let mut expected: Vec<String> = Vec::new();
+                for rule in positives {
+                    let name = describe_rule(rule).to_string();
+                    if !expected.contains(&name) {
+                        expected.push(name);
+                    }
+                }
+                (expected, None)
+            }
+            ErrorVariant::CustomError { message } => (Vec::new(), Some(message.clone())),
+        };
 
-    build_ast(pairs)
+        // Underline the whole span if there is one, otherwise a single caret
+        let source_line = input.lines().nth(line - 1).unwrap_or("");
+        let width = input[offset..end.max(offset)].chars().count().max(1);
+        let snippet = format!("{}\n{}{}", source_line, " ".repeat(column - 1), "^".repeat(width));
+
+        ParseError { offset, line, column, expected, reason, snippet }
+    }
+
+    pub fn message(&self) -> String {
+        if let Some(reason) = &self.reason {
+            return reason.clone();
+        }
+        match self.expected.len() {
+            0 => "unexpected input".to_string(),
+            1 => format!("expected {}", self.expected[0]),
+            n => format!(
+                "expected {} or {}",
+                self.expected[..n - 1].join(", "),
+                self.expected[n - 1]
+            ),
+        }
+    }
 }
 
-fn build_ast(pair: pest::iterators::Pair<Rule>) -> Expr {
-    match pair.as_rule() {
-        Rule::ident => Expr::Var(pair.as_str().to_string()),
-        Rule::not => {
-            let text = pair.as_str().to_string();
-            let mut inner = pair.into_inner();
-            let mut expr = build_ast(inner.next().unwrap());
-            //Apply NOT operators if present 
-            for _ in text.chars().filter(|&c| c == '!' || c == '\'') {
-                expr = Expr::Not(Box::new(expr));
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}\n{}", self.line, self.column, self.message(), self.snippet)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn describe_rule(rule: &Rule) -> &'static str {
+    match rule {
+        Rule::EOI => "end of input",
+        Rule::ident | Rule::constant | Rule::primary | Rule::not | Rule::not_op => "a variable or '('",
+        Rule::or_op | Rule::nor_op | Rule::xor_op | Rule::xnor_op
+        | Rule::and_op | Rule::nand_op | Rule::prime
+        | Rule::iff_op | Rule::implies_op | Rule::converse_op => "an operator",
+        Rule::rparen => "')'",
+        Rule::assign => "'='",
+        Rule::let_kw => "'let'",
+        Rule::dc_kw => "'dc'",
+        Rule::separator => "';'",
+        Rule::index | Rule::indices => "a row number",
+        Rule::sigma | Rule::pi => "Σm(...) or ΠM(...)",
+        Rule::dont_cares => "d(...)",
+        _ => "an expression",
+    }
+}
+
+// Error for input that matches the grammar but is rejected while building the tree
+fn custom_error(span: pest::Span, message: &str) -> ParseError {
+    let err = pest::error::Error::new_from_span(
+        ErrorVariant::CustomError { message: message.to_string() },
+        span,
+    );
+    ParseError::from_pest(err, span.get_input())
+}
+
+// Which notation the input is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    // Multi-letter names, every AND written out: `Enable & !Reset`
+    #[default]
+    Standard,
+    // Single-letter names (with optional digit subscripts), AND by juxtaposition: `AB' + C1(A+B)'`
+    Textbook,
+}
+
+impl Dialect {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dialect::Standard => "standard",
+            Dialect::Textbook => "textbook",
+        }
+    }
+
+    pub fn toggle(&self) -> Self {
+        match self {
+            Dialect::Standard => Dialect::Textbook,
+            Dialect::Textbook => Dialect::Standard,
+        }
+    }
+}
+
+fn parse(rule: Rule, input: &str, dialect: Dialect) -> Result<pest::iterators::Pairs<'_, Rule>, ParseError> {
+    BooleanParser::parse(rule, input).map_err(|e| {
+        let mut err = ParseError::from_pest(e, input);
+        // The grammar always allows juxtaposition, so after a complete operand it
+        // would suggest another variable, which only the textbook dialect accepts
+        if dialect == Dialect::Standard && err.expected.iter().any(|e| e == "an operator") {
+            err.expected.retain(|e| e != "a variable or '('");
+        }
+        err
+    })
+}
+
+// Expressions joined by `=`, in order
+pub fn parse_chain(input: &str, dialect: Dialect) -> Result<Vec<Expr>, ParseError> {
+    parse(Rule::chain, input, dialect)?
+        .filter(|pair| pair.as_rule() == Rule::expr)
+        .map(|pair| build_ast(pair, dialect))
+        .collect()
+}
+
+pub fn parse_system(input: &str, dialect: Dialect) -> Result<System, ParseError> {
+    let pair = parse(Rule::input, input, dialect)?.next().unwrap();
+
+    let mut inputs: Vec<String> = Vec::new();
+    let mut definitions: Vec<Definition> = Vec::new();
+    let mut outputs: Vec<Output> = Vec::new();
+    let mut names = Vec::new();
+    let mut conditions = Vec::new();
+    let mut unnamed = false;
+    for statement in pair.into_inner() {
+        match statement.as_rule() {
+            Rule::definition | Rule::equation => {
+                let is_wire = statement.as_rule() == Rule::definition;
+                let mut inner = statement.into_inner().filter(|p| p.as_rule() == Rule::ident || p.as_rule() == Rule::expr);
+                let name = inner.next().unwrap();
+                let expr = resolve_refs(build_ast(inner.next().unwrap(), dialect)?, &definitions);
+
+                if names.iter().any(|n: &pest::iterators::Pair<Rule>| n.as_str() == name.as_str()) {
+                    return Err(custom_error(name.as_span(), "name is defined more than once"));
+                }
+                names.push(name.clone());
+
+                let name = name.as_str().to_string();
+                if is_wire {
+                    definitions.push(Definition { name, expr });
+                } else {
+                    outputs.push(Output { name, expr, dont_care: None });
+                }
             }
-            expr
+            Rule::dc_condition => {
+                let mut inner = statement.into_inner().filter(|p| p.as_rule() == Rule::ident || p.as_rule() == Rule::expr);
+                let name = inner.next().unwrap();
+                let expr = resolve_refs(build_ast(inner.next().unwrap(), dialect)?, &definitions);
+                conditions.push((name, expr));
+            }
+            Rule::function => {
+                let (name, vars, expr, dont_care) = build_function(statement)?;
+                if names.iter().any(|n| n.as_str() == name.as_str()) {
+                    return Err(custom_error(name.as_span(), "name is defined more than once"));
+                }
+                names.push(name.clone());
+
+                for var in vars {
+                    if !inputs.contains(&var) && !definitions.iter().any(|d| d.name == var) {
+                        inputs.push(var);
+                    }
+                }
+                outputs.push(Output {
+                    name: name.as_str().to_string(),
+                    expr: resolve_refs(expr, &definitions),
+                    dont_care: dont_care.map(|dc| resolve_refs(dc, &definitions)),
+                });
+            }
+            Rule::expr => {
+                if unnamed {
+                    return Err(custom_error(statement.as_span(), "only one unnamed output is allowed, name the others like F = ..."));
+                }
+                unnamed = true;
+                let expr = resolve_refs(build_ast(statement, dialect)?, &definitions);
+                outputs.push(Output { name: "OUT".to_string(), expr, dont_care: None });
+            }
+            _ => {}
         }
-        Rule::and => {
-            let mut inner = pair.into_inner();
-            let first = build_ast(inner.next().unwrap());
-            inner.fold(first, |left, next| Expr::And(Box::new(left), Box::new(build_ast(next))))
+    }
+
+    if outputs.is_empty() {
+        let end = pest::Span::new(input, input.len(), input.len()).unwrap();
+        return Err(custom_error(end, "no outputs, add an equation like F = ... or a plain expression"));
+    }
+
+    // A don't-care condition can come before or after its output, and adds to
+    // any other conditions (or `d(...)` rows) of that output
+    for (name, condition) in conditions {
+        let output = outputs.iter_mut().find(|o| o.name == name.as_str())
+            .ok_or_else(|| custom_error(name.as_span(), "no output with this name"))?;
+        output.dont_care = Some(match output.dont_care.take() {
+            Some(existing) => Expr::Or(Box::new(existing), Box::new(condition)),
+            None => condition,
+        });
+    }
+
+    // Outputs are not wires that can be read back (that would need feedback), and a
+    // wire that is read before its `let` would silently become an input
+    let used: Vec<String> = definitions.iter().map(|d| &d.expr)
+        .chain(outputs.iter().map(|o| &o.expr))
+        .chain(outputs.iter().filter_map(|o| o.dont_care.as_ref()))
+        .flat_map(variables)
+        .collect();
+    if let Some(name) = names.iter().find(|n| used.iter().any(|v| v == n.as_str())) {
+        let message = if definitions.iter().any(|d| d.name == name.as_str()) {
+            "wire is used before its definition"
+        } else {
+            "output name is also used as an input variable"
+        };
+        return Err(custom_error(name.as_span(), message));
+    }
+
+    Ok(System { inputs, definitions, outputs })
+}
+
+// Minterm lists address at most this many variables
+const MAX_FUNCTION_VARIABLES: usize = 20;
+
+// `F(A,B,C) = Σm(1,3) + d(5)` as a canonical sum of minterms (or a product of
+// maxterms for ΠM), plus the don't-care rows
+type Function<'i> = (pest::iterators::Pair<'i, Rule>, Vec<String>, Expr, Option<Expr>);
+
+fn build_function<'i>(pair: pest::iterators::Pair<'i, Rule>) -> Result<Function<'i>, ParseError> {
+    let mut inner = pair.into_inner();
+    let name = inner.next().unwrap();
+
+    let mut vars: Vec<String> = Vec::new();
+    let mut terms = None;
+    let mut dont_cares = None;
+    for part in inner {
+        match part.as_rule() {
+            Rule::ident => {
+                if vars.iter().any(|v| v == part.as_str()) {
+                    return Err(custom_error(part.as_span(), "variable is listed more than once"));
+                }
+                if vars.len() == MAX_FUNCTION_VARIABLES {
+                    return Err(custom_error(part.as_span(), "too many variables for a minterm list"));
+                }
+                vars.push(part.as_str().to_string());
+            }
+            Rule::terms => terms = Some(part),
+            Rule::dont_cares => dont_cares = Some(part),
+            _ => {}
         }
-        Rule::or => {
-            let mut inner = pair.into_inner();
-            let first = build_ast(inner.next().unwrap());
-            inner.fold(first, |left, next| Expr::Or(Box::new(left), Box::new(build_ast(next))))
+    }
+
+    let rows = 1usize << vars.len();
+    let mut terms = terms.unwrap().into_inner();
+    let is_sum = terms.next().unwrap().as_rule() == Rule::sigma;
+    let on = row_indices(terms.find(|p| p.as_rule() == Rule::indices).unwrap(), rows)?;
+    let expr = if is_sum {
+        Expr::any(on.iter().map(|&(row, _)| Expr::minterm(&vars, row)))
+    } else {
+        Expr::all(on.iter().map(|&(row, _)| Expr::maxterm(&vars, row)))
+    };
+
+    let dont_care = match dont_cares {
+        Some(dont_cares) => {
+            let indices = dont_cares.into_inner().find(|p| p.as_rule() == Rule::indices).unwrap();
+            let dc = row_indices(indices, rows)?;
+            if let Some((_, span)) = dc.iter().find(|(row, _)| on.iter().any(|(r, _)| r == row)) {
+                let kind = if is_sum { "minterm" } else { "maxterm" };
+                return Err(custom_error(*span, &format!("row is both a {} and a don't-care", kind)));
+            }
+            Some(Expr::any(dc.iter().map(|&(row, _)| Expr::minterm(&vars, row))))
         }
-        Rule::xor => {
-            let mut inner = pair.into_inner();
-            let first = build_ast(inner.next().unwrap());
-            inner.fold(first, |left, next| Expr::Xor(Box::new(left), Box::new(build_ast(next))))
+        None => None,
+    };
+
+    Ok((name, vars, expr, dont_care))
+}
+
+fn row_indices<'i>(
+    pair: pest::iterators::Pair<'i, Rule>,
+    rows: usize,
+) -> Result<Vec<(usize, pest::Span<'i>)>, ParseError> {
+    let mut indices: Vec<(usize, pest::Span<'i>)> = Vec::new();
+    for index in pair.into_inner() {
+        let row = index.as_str().parse::<usize>().ok()
+            .filter(|&row| row < rows)
+            .ok_or_else(|| custom_error(index.as_span(), &format!("row number must be below {}", rows)))?;
+        if !indices.iter().any(|&(r, _)| r == row) {
+            indices.push((row, index.as_span()));
         }
+    }
+    Ok(indices)
+}
 
-        Rule::xnor => {
-            let mut inner = pair.into_inner();
-            let first = build_ast(inner.next().unwrap());
-            inner.fold(first, |left, next| Expr::Xnor(Box::new(left), Box::new(build_ast(next))))
+// Turn variables that name an earlier `let` into references to that wire
+fn resolve_refs(expr: Expr, wires: &[Definition]) -> Expr {
+    let resolve = |e: Box<Expr>| Box::new(resolve_refs(*e, wires));
+    match expr {
+        Expr::Var(name) if wires.iter().any(|w| w.name == name) => Expr::Ref(name),
+        Expr::Var(_) | Expr::Const(_) | Expr::Ref(_) => expr,
+        Expr::Not(inner) => Expr::Not(resolve(inner)),
+        Expr::And(a, b) => Expr::And(resolve(a), resolve(b)),
+        Expr::Or(a, b) => Expr::Or(resolve(a), resolve(b)),
+        Expr::Xor(a, b) => Expr::Xor(resolve(a), resolve(b)),
+        Expr::Xnor(a, b) => Expr::Xnor(resolve(a), resolve(b)),
+        Expr::Nand(a, b) => Expr::Nand(resolve(a), resolve(b)),
+        Expr::Nor(a, b) => Expr::Nor(resolve(a), resolve(b)),
+        Expr::Implies(a, b) => Expr::Implies(resolve(a), resolve(b)),
+        Expr::Iff(a, b) => Expr::Iff(resolve(a), resolve(b)),
+    }
+}
+
+// Left-fold `operand (op operand)*`, skipping the operator pairs
+fn fold_binary(
+    pair: pest::iterators::Pair<Rule>,
+    dialect: Dialect,
+    make: fn(Box<Expr>, Box<Expr>) -> Expr,
+) -> Result<Expr, ParseError> {
+    let mut inner = pair.into_inner();
+    let mut expr = build_ast(inner.next().unwrap(), dialect)?;
+    let mut explicit = false;
+    for next in inner {
+        if is_operator(next.as_rule()) {
+            explicit = true;
+            continue;
         }
+        if !explicit && dialect == Dialect::Standard {
+            return Err(custom_error(
+                next.as_span(),
+                "missing operator (implicit AND needs the textbook dialect)",
+            ));
+        }
+        expr = make(Box::new(expr), Box::new(build_ast(next, dialect)?));
+        explicit = false;
+    }
+    Ok(expr)
+}
 
-        Rule::nor => {
-            let mut inner = pair.into_inner();
-            let first = build_ast(inner.next().unwrap());
-            inner.fold(first, |left, next| Expr::Nor(Box::new(left), Box::new(build_ast(next))))
+fn is_operator(rule: Rule) -> bool {
+    matches!(
+        rule,
+        Rule::or_op | Rule::nor_op | Rule::xor_op | Rule::xnor_op | Rule::and_op | Rule::nand_op
+        | Rule::iff_op | Rule::implies_op | Rule::converse_op
+    )
+}
+
+// Split a textbook identifier like `AB'C1` into its single-letter variables
+fn textbook_variables(ident: pest::iterators::Pair<Rule>) -> Result<Vec<Expr>, ParseError> {
+    let text = ident.as_str();
+    if !text.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(custom_error(ident.as_span(), "variable names must start with a letter"));
+    }
+
+    let mut vars: Vec<String> = Vec::new();
+    for c in text.chars() {
+        match vars.last_mut() {
+            Some(name) if c.is_ascii_digit() => name.push(c),
+            _ => vars.push(c.to_string()),
         }
+    }
+    Ok(vars.into_iter().map(Expr::Var).collect())
+}
 
-        Rule::nand => {
+fn negate(expr: Expr, times: usize) -> Expr {
+    (0..times).fold(expr, |e, _| Expr::Not(Box::new(e)))
+}
+
+fn build_ast(pair: pest::iterators::Pair<Rule>, dialect: Dialect) -> Result<Expr, ParseError> {
+    match pair.as_rule() {
+        Rule::ident => Ok(Expr::Var(pair.as_str().to_string())),
+        Rule::constant => {
+            let text = pair.as_str();
+            Ok(Expr::Const(text == "1" || text.eq_ignore_ascii_case("true")))
+        }
+        Rule::not => {
+            let mut prefix = 0;
+            let mut postfix = 0;
+            let mut primary = None;
+            for part in pair.into_inner() {
+                match part.as_rule() {
+                    Rule::not_op => prefix += 1,
+                    Rule::prime => postfix += 1,
+                    _ => primary = Some(part),
+                }
+            }
+            let primary = primary.unwrap();
+
+            // In `A'B` style input a run of letters is several variables, and the
+            // prefix and postfix NOTs belong to the first and last of them
+            if dialect == Dialect::Textbook {
+                let ident = primary.clone().into_inner().next().unwrap();
+                if ident.as_rule() == Rule::ident {
+                    let mut vars = textbook_variables(ident)?;
+                    let first = vars.remove(0);
+                    vars.insert(0, negate(first, prefix));
+                    let last = vars.pop().unwrap();
+                    vars.push(negate(last, postfix));
+
+                    let mut vars = vars.into_iter();
+                    let first = vars.next().unwrap();
+                    return Ok(vars.fold(first, |left, v| Expr::And(Box::new(left), Box::new(v))));
+                }
+            }
+
+            //Apply NOT operators if present
+            Ok(negate(build_ast(primary, dialect)?, prefix + postfix))
+        }
+        Rule::iff => fold_binary(pair, dialect, Expr::Iff),
+        Rule::implies => {
             let mut inner = pair.into_inner();
-            let first = build_ast(inner.next().unwrap());
-            inner.fold(first, |left, next| Expr::Nand(Box::new(left), Box::new(build_ast(next))))
+            let left = build_ast(inner.next().unwrap(), dialect)?;
+            match (inner.next(), inner.next()) {
+                (Some(op), Some(right)) => {
+                    let right = build_ast(right, dialect)?;
+                    // `A <- B` is the converse, B -> A
+                    if op.as_rule() == Rule::converse_op {
+                        Ok(Expr::Implies(Box::new(right), Box::new(left)))
+                    } else {
+                        Ok(Expr::Implies(Box::new(left), Box::new(right)))
+                    }
+                }
+                _ => Ok(left),
+            }
         }
-        Rule::expr | Rule::primary => build_ast(pair.into_inner().next().unwrap()),
+        Rule::and => fold_binary(pair, dialect, Expr::And),
+        Rule::or => fold_binary(pair, dialect, Expr::Or),
+        Rule::xor => fold_binary(pair, dialect, Expr::Xor),
+        Rule::xnor => fold_binary(pair, dialect, Expr::Xnor),
+        Rule::nor => fold_binary(pair, dialect, Expr::Nor),
+        Rule::nand => fold_binary(pair, dialect, Expr::Nand),
+        Rule::expr | Rule::primary => build_ast(pair.into_inner().next().unwrap(), dialect),
         _ => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printed(input: &str, dialect: Dialect) -> String {
+        let system = parse_system(input, dialect).unwrap_or_else(|err| panic!("{}: {}", input, err));
+        system.outputs[0].expr.to_string()
+    }
+
+    // `input` in `dialect` parses to the same tree as fully written-out standard input
+    fn same(input: &str, dialect: Dialect, standard: &str) {
+        assert_eq!(printed(input, dialect), printed(standard, Dialect::Standard), "{}", input);
+    }
+
+    #[test]
+    fn precedence() {
+        let cases = [
+            ("A | B & C", "A | (B & C)"),
+            ("A ^ B & C", "A ^ (B & C)"),
+            ("A | B ^ C", "A | (B ^ C)"),
+            ("A !| B ^ C", "A !| (B ^ C)"),
+            ("A !& B & C", "(A !& B) & C"),
+            ("!A & B", "(!A) & B"),
+            ("A -> B | C", "A -> (B | C)"),
+            ("A <-> B -> C", "A <-> (B -> C)"),
+        ];
+        for (input, grouped) in cases {
+            same(input, Dialect::Standard, grouped);
+        }
+    }
+
+    #[test]
+    fn implication_is_right_associative() {
+        same("A -> B -> C", Dialect::Standard, "A -> (B -> C)");
+        assert_ne!(printed("A -> B -> C", Dialect::Standard), printed("(A -> B) -> C", Dialect::Standard));
+        same("A <- B", Dialect::Standard, "B -> A");
+    }
+
+    #[test]
+    fn textbook_juxtaposition_and_primes() {
+        let cases = [
+            ("AB'", "A & !B"),
+            ("A'B", "!A & B"),
+            ("AB + C", "A & B | C"),
+            ("A(B + C)", "A & (B | C)"),
+            ("(A + B)'", "!(A | B)"),
+            ("(AB)'C", "!(A & B) & C"),
+            ("A1B2'", "A1 & !B2"),
+            ("A''", "!!A"),
+        ];
+        for (input, standard) in cases {
+            same(input, Dialect::Textbook, standard);
+        }
+    }
+
+    #[test]
+    fn unicode_operators() {
+        same("A ∧ ¬B ∨ C", Dialect::Standard, "A & !B | C");
+        same("A ⊕ B ⊙ C", Dialect::Standard, "A ^ B !^ C");
+        same("A → B ↔ C", Dialect::Standard, "A -> B <-> C");
+        same("A ↑ B ↓ C", Dialect::Standard, "A !& B !| C");
+        same("A · B'", Dialect::Textbook, "A & !B");
+    }
+
+    #[test]
+    fn standard_reads_names_whole() {
+        // `AB` is one variable in the standard dialect and two in the textbook one
+        same("AB'", Dialect::Standard, "!AB");
+        let system = parse_system("AB", Dialect::Standard).unwrap();
+        assert_eq!(variables(&system.outputs[0].expr), vec!["AB".to_string()]);
+        let system = parse_system("AB", Dialect::Textbook).unwrap();
+        assert_eq!(variables(&system.outputs[0].expr), vec!["A".to_string(), "B".to_string()]);
+    }
+
+    #[test]
+    fn standard_rejects_juxtaposition() {
+        let err = parse_system("A B", Dialect::Standard).unwrap_err();
+        assert!(err.message().contains("textbook"), "{}", err);
+        assert!(parse_system("A (B | C)", Dialect::Standard).is_err());
+        assert!(parse_system("A (B | C)", Dialect::Textbook).is_ok());
+    }
+}
@@ -0,0 +1,374 @@
+use crate::espresso::{complement, espresso_multi, output_covers, MAX_CUBES, MAX_CUBE_VARIABLES};
+use crate::expr::{Expr, Output, System};
+use crate::minimize::Implicant;
+use crate::truth_table::system_variables;
+use std::fmt;
+
+// A Berkeley PLA file: a cube list per output over shared inputs
+#[derive(Debug, Clone)]
+pub struct Pla {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub on: Vec<Vec<Implicant>>,
+    pub dont_cares: Vec<Vec<Implicant>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlaError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PlaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PlaError {}
+
+// Names for files without `.ilb`: A, B, C... or x0, x1... past 26 inputs
+fn default_inputs(count: usize) -> Vec<String> {
+    if count <= 26 {
+        (0..count).map(|i| ((b'A' + i as u8) as char).to_string()).collect()
+    } else {
+        (0..count).map(|i| format!("x{}", i)).collect()
+    }
+}
+
+// Names for files without `.ob`: F, or F0, F1..., with OUT in place of F
+// when that clashes with an input, then OUT1, OUT2...
+fn default_outputs(count: usize, inputs: &[String]) -> Vec<String> {
+    let names = |prefix: &str| -> Vec<String> {
+        if count == 1 {
+            vec![prefix.to_string()]
+        } else {
+            (0..count).map(|i| format!("{}{}", prefix, i)).collect()
+        }
+    };
+    let prefix = ["F".to_string(), "OUT".to_string()].into_iter()
+        .chain((1..).map(|i| format!("OUT{}", i)))
+        .find(|prefix| names(prefix).iter().all(|name| !inputs.contains(name)))
+        .unwrap();
+    names(&prefix)
+}
+
+// Reads `.i`, `.o`, `.ilb`, `.ob`, `.p`, `.type` (f, fd, fr or fdr) and `.e`.
+// In the output part `1` puts the cube in the on-set, `-` in the don't-care
+// set, and `0` in the off-set for the `r` types
+pub fn parse_pla(text: &str) -> Result<Pla, PlaError> {
+    let mut input_count: Option<usize> = None;
+    let mut output_count: Option<usize> = None;
+    let mut input_names: Option<Vec<String>> = None;
+    let mut output_names: Option<Vec<String>> = None;
+    let mut kind = "fd".to_string();
+    let mut rows: Vec<(usize, String, String)> = Vec::new();
+
+    for (number, raw) in text.lines().enumerate() {
+        let line_number = number + 1;
+        let error = |message: String| PlaError { line: line_number, message };
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut words = line.split_whitespace();
+        let first = words.next().unwrap_or("");
+        let count = |word: Option<&str>| -> Result<usize, PlaError> {
+            word.and_then(|w| w.parse().ok()).ok_or_else(|| error(format!("`{}` needs a number", first)))
+        };
+
+        match first {
+            ".i" => input_count = Some(count(words.next())?),
+            ".o" => output_count = Some(count(words.next())?),
+            ".ilb" => input_names = Some(words.map(str::to_string).collect()),
+            ".ob" => output_names = Some(words.map(str::to_string).collect()),
+            ".p" => { count(words.next())?; }
+            ".type" => {
+                kind = words.next().unwrap_or("").to_string();
+                if !["f", "fd", "fr", "fdr"].contains(&kind.as_str()) {
+                    return Err(error(format!("unsupported type `{}`", kind)));
+                }
+            }
+            ".e" | ".end" => break,
+            keyword if keyword.starts_with('.') => {
+                return Err(error(format!("unsupported keyword `{}`", keyword)));
+            }
+            _ => {
+                let inputs = first.to_string();
+                let outputs: String = words.collect();
+                rows.push((line_number, inputs, outputs));
+            }
+        }
+    }
+
+    let n = input_count.ok_or(PlaError { line: 1, message: "missing `.i`".to_string() })?;
+    let m = output_count.unwrap_or(1);
+    if n > MAX_CUBE_VARIABLES {
+        return Err(PlaError { line: 1, message: format!("at most {} inputs are supported", MAX_CUBE_VARIABLES) });
+    }
+
+    let inputs = input_names.unwrap_or_else(|| default_inputs(n));
+    let outputs = output_names.unwrap_or_else(|| default_outputs(m, &inputs));
+    if inputs.len() != n || outputs.len() != m {
+        return Err(PlaError { line: 1, message: "`.ilb` / `.ob` do not match `.i` / `.o`".to_string() });
+    }
+
+    let mut on = vec![Vec::new(); m];
+    let mut dont_cares = vec![Vec::new(); m];
+    let mut off = vec![Vec::new(); m];
+
+    for (line, input_part, output_part) in rows {
+        let error = |message: &str| PlaError { line, message: message.to_string() };
+        // With no inputs a row is just its output part, which then comes first.
+        // A single-output file may leave the output part out
+        let (input_part, output_part) = match (n, output_part.is_empty()) {
+            (0, true) => (String::new(), input_part),
+            (_, true) if m == 1 => (input_part, "1".to_string()),
+            _ => (input_part, output_part),
+        };
+        if input_part.chars().count() != n || output_part.chars().count() != m {
+            return Err(error("cube does not match the number of inputs and outputs"));
+        }
+
+        let mut cube = Implicant { value: 0, mask: 0 };
+        for (i, c) in input_part.chars().enumerate() {
+            let bit = 1 << (n - 1 - i);
+            match c {
+                '1' => cube.value |= bit,
+                '0' => {}
+                '-' | '2' => cube.mask |= bit,
+                _ => return Err(error("inputs must be 0, 1 or -")),
+            }
+        }
+
+        for (o, c) in output_part.chars().enumerate() {
+            match c {
+                '1' | '4' => on[o].push(cube),
+                '-' | '2' => dont_cares[o].push(cube),
+                '0' | '3' => off[o].push(cube),
+                '~' => {}
+                _ => return Err(error("outputs must be 0, 1, - or ~")),
+            }
+        }
+    }
+
+    // Without a `d` in the type the don't-care set is whatever is left over
+    // once the off-set is given, or nothing
+    for o in 0..m {
+        dont_cares[o] = match kind.as_str() {
+            "fr" => {
+                let mut given = on[o].clone();
+                given.extend_from_slice(&off[o]);
+                complement(&given, n)
+            }
+            "f" => Vec::new(),
+            _ => std::mem::take(&mut dont_cares[o]),
+        };
+    }
+
+    Ok(Pla { inputs, outputs, on, dont_cares })
+}
+
+impl Pla {
+    // The cube lists of every output of a system, over all its inputs
+    pub fn from_system(system: &System) -> Result<Pla, String> {
+        let inputs = system_variables(system);
+        if inputs.len() > MAX_CUBE_VARIABLES {
+            return Err(format!("at most {} inputs are supported for a PLA", MAX_CUBE_VARIABLES));
+        }
+        let mut on = Vec::new();
+        let mut dont_cares = Vec::new();
+        for output in &system.outputs {
+            let dont_care = output.dont_care.as_ref().map(|dont_care| system.inline(dont_care));
+            let [output_on, output_dont_cares, _] = output_covers(&system.inline(&output.expr), dont_care.as_ref(), &inputs)
+                .ok_or_else(|| format!("{} needs more than {} product terms for a PLA", output.name, MAX_CUBES))?;
+            on.push(output_on);
+            dont_cares.push(output_dont_cares);
+        }
+        let outputs = system.outputs.iter().map(|o| o.name.clone()).collect();
+        Ok(Pla { inputs, outputs, on, dont_cares })
+    }
+
+    // One sum of products per output
+    pub fn to_system(&self) -> System {
+        let product = |cube: &Implicant| cube.to_expr(&self.inputs);
+        let outputs = self.outputs.iter().enumerate()
+            .map(|(o, name)| Output {
+                name: name.clone(),
+                expr: Expr::any(self.on[o].iter().map(product)),
+                dont_care: (!self.dont_cares[o].is_empty())
+                    .then(|| Expr::any(self.dont_cares[o].iter().map(product))),
+            })
+            .collect();
+        System { inputs: self.inputs.clone(), definitions: Vec::new(), outputs }
+    }
+
+    // All outputs minimised together with `espresso_multi`, so a term shared
+    // between outputs is written as one row
+    pub fn minimized(&self) -> Pla {
+        let n = self.inputs.len();
+        let mut on = vec![Vec::new(); self.outputs.len()];
+        for term in espresso_multi(n, &self.on, &self.dont_cares) {
+            for &o in &term.outputs {
+                on[o].push(term.cube);
+            }
+        }
+        Pla {
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone(),
+            on,
+            dont_cares: vec![Vec::new(); self.outputs.len()],
+        }
+    }
+}
+
+// Writes type fd: a cube shared by several outputs is written once
+impl fmt::Display for Pla {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let n = self.inputs.len();
+        let m = self.outputs.len();
+
+        // Each distinct cube with its value for every output
+        let mut rows: Vec<(Implicant, Vec<char>)> = Vec::new();
+        for o in 0..m {
+            let sets = [(&self.on[o], '1'), (&self.dont_cares[o], '-')];
+            for (cubes, symbol) in sets {
+                for cube in cubes {
+                    match rows.iter_mut().find(|(c, outs)| c == cube && outs[o] == '0') {
+                        Some((_, outs)) => outs[o] = symbol,
+                        None => {
+                            let mut outs = vec!['0'; m];
+                            outs[o] = symbol;
+                            rows.push((*cube, outs));
+                        }
+                    }
+                }
+            }
+        }
+
+        writeln!(f, ".i {}", n)?;
+        writeln!(f, ".o {}", m)?;
+        writeln!(f, ".ilb {}", self.inputs.join(" "))?;
+        writeln!(f, ".ob {}", self.outputs.join(" "))?;
+        writeln!(f, ".type fd")?;
+        writeln!(f, ".p {}", rows.len())?;
+        for (cube, outs) in rows {
+            let inputs: String = (0..n)
+                .map(|i| {
+                    let bit = 1 << (n - 1 - i);
+                    if cube.mask & bit != 0 { '-' } else if cube.value & bit != 0 { '1' } else { '0' }
+                })
+                .collect();
+            writeln!(f, "{} {}", inputs, outs.into_iter().collect::<String>())?;
+        }
+        writeln!(f, ".e")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::random;
+
+    // Rows of an `n`-input function some cube of the list covers
+    fn rows(cubes: &[Implicant], n: usize) -> Vec<usize> {
+        (0..1 << n).filter(|&row| cubes.iter().any(|cube| cube.covers(row))).collect()
+    }
+
+    fn random_pla(seed: &mut u64, n: usize, m: usize) -> String {
+        let mut text = format!(".i {}\n.o {}\n", n, m);
+        for _ in 0..random(seed, 8) {
+            let inputs: String = (0..n).map(|_| ['0', '1', '-'][random(seed, 3)]).collect();
+            let outputs: String = (0..m).map(|_| ['0', '1', '1', '-'][random(seed, 4)]).collect();
+            text += &format!("{} {}\n", inputs, outputs);
+        }
+        text + ".e\n"
+    }
+
+    #[test]
+    fn types_decide_the_dont_cares() {
+        let rows_of = |kind: &str| {
+            let pla = parse_pla(&format!(".i 2\n.o 1\n.type {}\n11 1\n0- 0\n10 -\n", kind)).unwrap();
+            (rows(&pla.on[0], 2), rows(&pla.dont_cares[0], 2))
+        };
+        assert_eq!(rows_of("f"), (vec![3], vec![]));
+        assert_eq!(rows_of("fd"), (vec![3], vec![2]));
+        // Whatever is neither on nor off is free
+        assert_eq!(rows_of("fr"), (vec![3], vec![2]));
+        assert_eq!(rows_of("fdr"), (vec![3], vec![2]));
+
+        let pla = parse_pla(".i 2\n.o 1\n.type fr\n11 1\n").unwrap();
+        assert_eq!(rows(&pla.dont_cares[0], 2), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn espresso_symbols() {
+        // `2` is a don't-care in either part, `3` off, `4` on, `~` nothing
+        let pla = parse_pla(".i 3\n.o 2\n.type fdr\n1-2 4~\n000 32\n").unwrap();
+        assert_eq!(rows(&pla.on[0], 3), vec![4, 5, 6, 7]);
+        assert_eq!(rows(&pla.on[1], 3), Vec::<usize>::new());
+        assert_eq!(rows(&pla.dont_cares[1], 3), vec![0]);
+    }
+
+    #[test]
+    fn errors() {
+        let line = |text: &str| parse_pla(text).map(|_| ()).unwrap_err().line;
+        assert_eq!(line(".i 2\n.type fx\n"), 2);
+        assert_eq!(line(".i 2\n.o 1\n.mv 3\n"), 3);
+        assert_eq!(line(".i 2\n.o 1\n101 1\n"), 3);
+        assert_eq!(line(".i 2\n.o 2\n10 1\n"), 3);
+        assert_eq!(line(".i 2\n.o 1\n1x 1\n"), 3);
+        assert_eq!(line(".i 2\n.o 1\n10 x\n"), 3);
+        assert_eq!(line(".i 2\n.o 1\n.ilb A\n"), 1);
+        assert_eq!(parse_pla(".o 1\n10 1\n").unwrap_err().message, "missing `.i`");
+    }
+
+    #[test]
+    fn default_names_do_not_clash() {
+        let pla = parse_pla(".i 2\n.o 1\n11 1\n").unwrap();
+        assert_eq!((pla.inputs, pla.outputs), (vec!["A".to_string(), "B".to_string()], vec!["F".to_string()]));
+
+        // A to F are inputs here
+        let pla = parse_pla(".i 6\n.o 1\n111111 1\n").unwrap();
+        assert_eq!(pla.outputs, ["OUT"]);
+        let pla = parse_pla(".i 2\n.o 2\n.ilb F1 OUT0\n11 11\n").unwrap();
+        assert_eq!(pla.outputs, ["OUT10", "OUT11"]);
+    }
+
+    #[test]
+    fn written_files_read_back_the_same() {
+        let mut seed = 0x853c49e6748fea9b;
+        for _ in 0..200 {
+            let (n, m) = (1 + random(&mut seed, 4), 1 + random(&mut seed, 3));
+            let pla = parse_pla(&random_pla(&mut seed, n, m)).unwrap();
+            let again = parse_pla(&pla.to_string()).unwrap();
+            assert_eq!((&again.inputs, &again.outputs), (&pla.inputs, &pla.outputs));
+            for o in 0..m {
+                assert_eq!(rows(&again.on[o], n), rows(&pla.on[o], n), "{}", pla);
+                assert_eq!(rows(&again.dont_cares[o], n), rows(&pla.dont_cares[o], n), "{}", pla);
+            }
+        }
+    }
+
+    // Don't-care rows are free, so only the rest of each on-set has to be
+    // covered and the rest of the rows missed
+    #[test]
+    fn minimized_covers_each_output() {
+        let mut seed = 0xda3e39cb94b95bdb;
+        for _ in 0..200 {
+            let (n, m) = (1 + random(&mut seed, 5), 1 + random(&mut seed, 3));
+            let pla = parse_pla(&random_pla(&mut seed, n, m)).unwrap();
+            let minimized = pla.minimized();
+            for o in 0..m {
+                let (on, dont_cares) = (rows(&pla.on[o], n), rows(&pla.dont_cares[o], n));
+                let covered = rows(&minimized.on[o], n);
+                for row in 0..1 << n {
+                    if !dont_cares.contains(&row) {
+                        assert_eq!(covered.contains(&row), on.contains(&row), "{}", pla);
+                    }
+                }
+            }
+        }
+    }
+}
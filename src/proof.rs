@@ -0,0 +1,90 @@
+use crate::bdd::Bdd;
+use crate::derivation::{law_between, step_line};
+use crate::expr::Expr;
+use crate::truth_table::variables;
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use std::collections::HashMap;
+
+// What one `=` of a chain turns out to be
+pub enum Verdict {
+    Law(&'static str),  // the single law that justifies it
+    Equivalent,         // true, but not by any one law the rewrite rules know
+    // False; an assignment where the two sides differ, and the left side's value there
+    Invalid(Vec<(String, bool)>, bool),
+}
+
+// Checks each expression of a chain against the next
+pub fn check_proof(exprs: &[Expr]) -> Vec<Verdict> {
+    let mut vars: Vec<String> = Vec::new();
+    for expr in exprs {
+        for var in variables(expr) {
+            if !vars.contains(&var) {
+                vars.push(var);
+            }
+        }
+    }
+    let mut bdd = Bdd::new(vars.clone());
+    let roots: Vec<usize> = exprs.iter().map(|expr| bdd.build(expr, &HashMap::new())).collect();
+
+    exprs.windows(2).zip(roots.windows(2))
+        .map(|(pair, root)| {
+            let differ = bdd.xor(root[0], root[1]);
+            match bdd.satisfy_one(differ) {
+                // Variables the BDD leaves free can be anything, so take 0
+                Some(assignment) => {
+                    let mut inputs = vec![false; vars.len()];
+                    for (var, value) in assignment {
+                        inputs[var] = value;
+                    }
+                    let left = bdd.evaluate(root[0], &inputs);
+                    Verdict::Invalid(vars.iter().cloned().zip(inputs).collect(), left)
+                }
+                None => law_between(&pair[0], &pair[1]).map_or(Verdict::Equivalent, Verdict::Law),
+            }
+        })
+        .collect()
+}
+
+// The chain one expression per line, each with the law that justifies it,
+// and a verdict on the whole that points out the first wrong step
+pub fn proof_lines(exprs: &[Expr]) -> (bool, Vec<Line<'static>>) {
+    let verdicts = check_proof(exprs);
+    let mut lines = vec![step_line(Span::raw(""), ' ', &exprs[0], &[])];
+    for (expr, verdict) in exprs[1..].iter().zip(&verdicts) {
+        let law = match verdict {
+            Verdict::Law(law) => Span::styled(*law, Style::default().fg(Color::Green)),
+            Verdict::Equivalent => Span::styled("Several laws", Style::default().fg(Color::Green)),
+            Verdict::Invalid(..) => Span::styled("Not equal", Style::default().fg(Color::Red)),
+        };
+        lines.push(step_line(law, '=', expr, &[]));
+    }
+    lines.push(Line::from(""));
+
+    let steps = verdicts.len();
+    let first_invalid = verdicts.iter().enumerate().find_map(|(i, verdict)| match verdict {
+        Verdict::Invalid(assignment, left) => Some((i, assignment, *left)),
+        _ => None,
+    });
+    let Some((i, assignment, left)) = first_invalid else {
+        let summary = match steps {
+            1 => "The step is valid".to_string(),
+            _ => format!("All {} steps are valid", steps),
+        };
+        lines.push(Line::styled(summary, Style::default().fg(Color::Green)));
+        return (true, lines);
+    };
+    let values = assignment.iter()
+        .map(|(var, value)| format!("{}={}", var, *value as u8))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let at = if values.is_empty() { String::new() } else { format!("at {} ", values) };
+    let summary = format!(
+        "Step {} is wrong: {}the left side is {} and the right side is {}",
+        i + 1, at, left as u8, !left as u8
+    );
+    lines.push(Line::styled(summary, Style::default().fg(Color::Red)));
+    (false, lines)
+}
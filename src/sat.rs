@@ -0,0 +1,662 @@
+use crate::dag::{Dag, Node};
+use crate::expr::System;
+use crate::truth_table::{output_variables, MAX_TABLE_VARIABLES};
+use comfy_table::{
+    Table,
+    presets::UTF8_FULL,
+    modifiers::UTF8_ROUND_CORNERS,
+    ContentArrangement
+};
+
+// How many models the truth table tab lists for each output
+const MODEL_LIMIT: usize = 64;
+
+// Conflicts before the first restart; later ones follow the Luby sequence
+const RESTART_BASE: usize = 100;
+
+// VSIDS: every conflict makes earlier bumps worth this much less
+const ACTIVITY_DECAY: f64 = 0.95;
+
+// Learnt clauses kept before the first clean-up, and how many more each time
+const FIRST_REDUCE: usize = 2000;
+const REDUCE_STEP: usize = 500;
+
+// Learnt clauses whose literals span at most this many decision levels are
+// never thrown away
+const GLUE: usize = 2;
+
+// A formula in conjunctive normal form, with DIMACS literals: variable v is
+// `v` or `-v`, counting from 1
+#[derive(Debug, Clone, Default)]
+pub struct Cnf {
+    pub num_vars: usize,
+    pub clauses: Vec<Vec<i32>>,
+}
+
+// An output in CNF. Variables 1 to inputs.len() are the inputs, in order; the
+// rest stand for gates
+pub struct Encoding {
+    pub cnf: Cnf,
+    pub inputs: Vec<String>,
+}
+
+// Tseitin transformation: a new variable for every gate, with clauses making
+// it equal to the gate's function of its inputs, and one clause asserting the
+// output. The result is satisfiable exactly when the output can be 1, and
+// grows linearly with the expression. Gates come from the system's DAG, so a
+// repeated subexpression gets one variable. Don't-care conditions are left out
+pub fn tseitin(system: &System, output: usize) -> Encoding {
+    let inputs = output_variables(system, &system.outputs[output]);
+    let dag = Dag::from_system(system);
+    let root = dag.outputs[output].1;
+    let mut cnf = Cnf { num_vars: inputs.len(), clauses: Vec::new() };
+
+    // A literal equal to each node the output depends on; inverted gates
+    // reuse the plain one's variable, negated
+    let mut literals = vec![0; dag.nodes.len()];
+    for (id, reached) in dag.cone(root).into_iter().enumerate() {
+        if !reached {
+            continue;
+        }
+        let literal = |node: usize| literals[node];
+        literals[id] = match dag.nodes[id] {
+            Node::Input(i) => inputs.iter().position(|v| *v == dag.inputs[i]).unwrap() as i32 + 1,
+            Node::Const(value) => {
+                let x = fresh(&mut cnf);
+                cnf.clauses.push(vec![if value { x } else { -x }]);
+                x
+            }
+            Node::Not(a) => -literal(a),
+            Node::And(a, b) => gate(&mut cnf, Gate::And, literal(a), literal(b)),
+            Node::Nand(a, b) => -gate(&mut cnf, Gate::And, literal(a), literal(b)),
+            Node::Or(a, b) => gate(&mut cnf, Gate::Or, literal(a), literal(b)),
+            Node::Nor(a, b) => -gate(&mut cnf, Gate::Or, literal(a), literal(b)),
+            Node::Xor(a, b) => gate(&mut cnf, Gate::Xor, literal(a), literal(b)),
+            Node::Xnor(a, b) => -gate(&mut cnf, Gate::Xor, literal(a), literal(b)),
+        };
+    }
+    cnf.clauses.push(vec![literals[root]]);
+    Encoding { cnf, inputs }
+}
+
+fn fresh(cnf: &mut Cnf) -> i32 {
+    cnf.num_vars += 1;
+    cnf.num_vars as i32
+}
+
+// A new variable equal to the gate's function of `a` and `b`
+fn gate(cnf: &mut Cnf, gate: Gate, a: i32, b: i32) -> i32 {
+    let x = fresh(cnf);
+    let clauses = match gate {
+        Gate::And => vec![vec![-x, a], vec![-x, b], vec![x, -a, -b]],
+        Gate::Or => vec![vec![x, -a], vec![x, -b], vec![-x, a, b]],
+        Gate::Xor => vec![vec![-x, a, b], vec![-x, -a, -b], vec![x, -a, b], vec![x, a, -b]],
+    };
+    cnf.clauses.extend(clauses);
+    x
+}
+
+enum Gate {
+    And,
+    Or,
+    Xor,
+}
+
+// Solver literals: 2v for variable v, 2v+1 for its negation, counting from 0
+fn literal(dimacs: i32) -> usize {
+    (dimacs.unsigned_abs() as usize - 1) * 2 + (dimacs < 0) as usize
+}
+
+fn var(lit: usize) -> usize {
+    lit >> 1
+}
+
+fn value_of(values: &[Option<bool>], lit: usize) -> Option<bool> {
+    values[var(lit)].map(|value| value != (lit & 1 == 1))
+}
+
+// 1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8... counting from 0
+fn luby(mut i: usize) -> usize {
+    let (mut size, mut exponent) = (1, 0);
+    while size < i + 1 {
+        exponent += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        exponent -= 1;
+        i %= size;
+    }
+    1 << exponent
+}
+
+// Conflict-driven clause learning: unit propagation over two watched literals
+// per clause, first-UIP learning with non-chronological backtracking, VSIDS
+// decisions with phase saving, and Luby restarts
+pub struct Solver {
+    clauses: Vec<Vec<usize>>,
+    glue: Vec<Option<usize>>,      // decision levels in each learnt clause
+    reduce_at: usize,
+    watches: Vec<Vec<usize>>,      // clauses watching each literal
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,   // clause that implied each variable
+    trail: Vec<usize>,             // assigned literals, in order
+    trail_limits: Vec<usize>,      // where each decision level starts
+    propagated: usize,
+    activity: Vec<f64>,
+    bump: f64,
+    heap: Vec<usize>,              // unassigned variables, most active first
+    heap_index: Vec<Option<usize>>,
+    phases: Vec<bool>,
+    seen: Vec<bool>,
+    unsatisfiable: bool,
+}
+
+impl Solver {
+    pub fn new(cnf: &Cnf) -> Solver {
+        let n = cnf.num_vars;
+        let mut solver = Solver {
+            clauses: Vec::new(),
+            glue: Vec::new(),
+            reduce_at: FIRST_REDUCE,
+            watches: vec![Vec::new(); 2 * n],
+            values: vec![None; n],
+            levels: vec![0; n],
+            reasons: vec![None; n],
+            trail: Vec::new(),
+            trail_limits: Vec::new(),
+            propagated: 0,
+            activity: vec![0.0; n],
+            bump: 1.0,
+            heap: Vec::new(),
+            heap_index: vec![None; n],
+            phases: vec![false; n],
+            seen: vec![false; n],
+            unsatisfiable: false,
+        };
+        for v in 0..n {
+            solver.heap_insert(v);
+        }
+        for clause in &cnf.clauses {
+            solver.add_clause(clause);
+        }
+        solver
+    }
+
+    fn level(&self) -> usize {
+        self.trail_limits.len()
+    }
+
+    // Add a clause of DIMACS literals. Can be called between calls to `solve`
+    pub fn add_clause(&mut self, clause: &[i32]) {
+        self.backtrack(0);
+        let mut lits: Vec<usize> = clause.iter().map(|&l| literal(l)).collect();
+        lits.sort();
+        lits.dedup();
+        if lits.windows(2).any(|w| w[0] ^ 1 == w[1]) || lits.iter().any(|&l| value_of(&self.values, l) == Some(true)) {
+            return;
+        }
+        lits.retain(|&l| value_of(&self.values, l).is_none());
+
+        match lits.len() {
+            0 => self.unsatisfiable = true,
+            1 => {
+                self.assign(lits[0], None);
+                if self.propagate().is_some() {
+                    self.unsatisfiable = true;
+                }
+            }
+            _ => { self.attach(lits, None); }
+        }
+    }
+
+    fn attach(&mut self, lits: Vec<usize>, glue: Option<usize>) -> usize {
+        let index = self.clauses.len();
+        self.watches[lits[0]].push(index);
+        self.watches[lits[1]].push(index);
+        self.clauses.push(lits);
+        self.glue.push(glue);
+        index
+    }
+
+    // Throw away the less useful half of the learnt clauses, those spanning
+    // the most decision levels. Only called at level 0, where no reasons are
+    // needed, so clauses can be renumbered
+    fn reduce(&mut self) {
+        let mut learnt: Vec<usize> = (0..self.clauses.len())
+            .filter(|&i| self.glue[i].is_some_and(|glue| glue > GLUE))
+            .collect();
+        learnt.sort_by_key(|&i| std::cmp::Reverse((self.glue[i], self.clauses[i].len())));
+        let mut remove = vec![false; self.clauses.len()];
+        for &i in &learnt[..learnt.len() / 2] {
+            remove[i] = true;
+        }
+
+        let clauses = std::mem::take(&mut self.clauses);
+        let glue = std::mem::take(&mut self.glue);
+        for watchers in &mut self.watches {
+            watchers.clear();
+        }
+        for reason in &mut self.reasons {
+            *reason = None;
+        }
+        for ((lits, glue), remove) in clauses.into_iter().zip(glue).zip(remove) {
+            if !remove {
+                self.attach(lits, glue);
+            }
+        }
+    }
+
+    fn assign(&mut self, lit: usize, reason: Option<usize>) {
+        let v = var(lit);
+        self.values[v] = Some(lit & 1 == 0);
+        self.levels[v] = self.level();
+        self.reasons[v] = reason;
+        self.trail.push(lit);
+    }
+
+    // Assign everything the current assignment forces. Gives the clause that
+    // became false, if any. The implied literal of a clause is kept first
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let false_lit = self.trail[self.propagated] ^ 1;
+            self.propagated += 1;
+            let mut watchers = std::mem::take(&mut self.watches[false_lit]);
+            let mut i = 0;
+            let mut conflict = None;
+            while i < watchers.len() {
+                let index = watchers[i];
+                let clause = &mut self.clauses[index];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                if value_of(&self.values, clause[0]) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+
+                // Watch another literal that is not false, if there is one
+                if let Some(k) = (2..clause.len()).find(|&k| value_of(&self.values, clause[k]) != Some(false)) {
+                    clause.swap(1, k);
+                    self.watches[clause[1]].push(index);
+                    watchers.swap_remove(i);
+                    continue;
+                }
+
+                let first = clause[0];
+                if value_of(&self.values, first) == Some(false) {
+                    conflict = Some(index);
+                    break;
+                }
+                self.assign(first, Some(index));
+                i += 1;
+            }
+            self.watches[false_lit] = watchers;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    // First-UIP learning: resolve the conflict clause with the reasons of
+    // the current level's literals, latest first, until one literal of that
+    // level is left. Gives the learnt clause, that literal first, and the
+    // level to go back to
+    fn analyze(&mut self, conflict: usize) -> (Vec<usize>, usize) {
+        let mut learnt = vec![0];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut clause = conflict;
+        let mut skip_first = false;
+
+        loop {
+            for k in skip_first as usize..self.clauses[clause].len() {
+                let lit = self.clauses[clause][k];
+                let v = var(lit);
+                if !self.seen[v] && self.levels[v] > 0 {
+                    self.seen[v] = true;
+                    self.bump_activity(v);
+                    if self.levels[v] == self.level() {
+                        pending += 1;
+                    } else {
+                        learnt.push(lit);
+                    }
+                }
+            }
+
+            loop {
+                index -= 1;
+                if self.seen[var(self.trail[index])] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            self.seen[var(lit)] = false;
+            pending -= 1;
+            if pending == 0 {
+                learnt[0] = lit ^ 1;
+                break;
+            }
+            clause = self.reasons[var(lit)].unwrap();
+            skip_first = true;
+        }
+
+        // Drop literals implied by the rest of the clause: those whose reason
+        // only has literals already in it, or fixed at level 0
+        let all = learnt.clone();
+        learnt = std::iter::once(all[0])
+            .chain(all[1..].iter().copied().filter(|&lit| {
+                let Some(reason) = self.reasons[var(lit)] else { return true };
+                !self.clauses[reason][1..].iter().all(|&other| self.seen[var(other)] || self.levels[var(other)] == 0)
+            }))
+            .collect();
+        for &lit in &all[1..] {
+            self.seen[var(lit)] = false;
+        }
+
+        // Watch the literal of the highest remaining level second, so the
+        // clause is unit right after backtracking
+        let mut back_to = 0;
+        if learnt.len() > 1 {
+            let highest = (1..learnt.len()).max_by_key(|&k| self.levels[var(learnt[k])]).unwrap();
+            learnt.swap(1, highest);
+            back_to = self.levels[var(learnt[1])];
+        }
+        (learnt, back_to)
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.level() <= level {
+            return;
+        }
+        let start = self.trail_limits[level];
+        for k in (start..self.trail.len()).rev() {
+            let lit = self.trail[k];
+            let v = var(lit);
+            self.phases[v] = lit & 1 == 0;
+            self.values[v] = None;
+            self.reasons[v] = None;
+            self.heap_insert(v);
+        }
+        self.trail.truncate(start);
+        self.trail_limits.truncate(level);
+        self.propagated = start;
+    }
+
+    fn bump_activity(&mut self, v: usize) {
+        self.activity[v] += self.bump;
+        if self.activity[v] > 1e100 {
+            for activity in &mut self.activity {
+                *activity *= 1e-100;
+            }
+            self.bump *= 1e-100;
+        }
+        if let Some(position) = self.heap_index[v] {
+            self.sift_up(position);
+        }
+    }
+
+    // A model of every variable, or None if there is none. Call again after
+    // `add_clause` to look for another
+    pub fn solve(&mut self) -> Option<Vec<bool>> {
+        if self.unsatisfiable {
+            return None;
+        }
+        let mut restarts = 0;
+        let mut conflicts = 0;
+        let mut restart_at = RESTART_BASE;
+
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.level() == 0 {
+                    self.unsatisfiable = true;
+                    return None;
+                }
+                conflicts += 1;
+                let (learnt, back_to) = self.analyze(conflict);
+                self.backtrack(back_to);
+                if learnt.len() == 1 {
+                    self.assign(learnt[0], None);
+                } else {
+                    let first = learnt[0];
+                    let mut levels: Vec<usize> = learnt.iter().map(|&lit| self.levels[var(lit)]).collect();
+                    levels.sort();
+                    levels.dedup();
+                    let index = self.attach(learnt, Some(levels.len()));
+                    self.assign(first, Some(index));
+                }
+                self.bump /= ACTIVITY_DECAY;
+                continue;
+            }
+
+            if conflicts >= restart_at {
+                restarts += 1;
+                conflicts = 0;
+                restart_at = RESTART_BASE * luby(restarts);
+                self.backtrack(0);
+                if self.glue.iter().filter(|glue| glue.is_some()).count() > self.reduce_at {
+                    self.reduce();
+                    self.reduce_at += REDUCE_STEP;
+                }
+                continue;
+            }
+
+            let Some(v) = self.pick_variable() else {
+                return Some(self.values.iter().map(|value| value.unwrap()).collect());
+            };
+            self.trail_limits.push(self.trail.len());
+            self.assign(2 * v + !self.phases[v] as usize, None);
+        }
+    }
+
+    fn pick_variable(&mut self) -> Option<usize> {
+        while let Some(v) = self.heap_pop() {
+            if self.values[v].is_none() {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    // Binary max-heap of variables by activity, with each one's position
+    fn heap_insert(&mut self, v: usize) {
+        if self.heap_index[v].is_some() {
+            return;
+        }
+        self.heap.push(v);
+        self.heap_index[v] = Some(self.heap.len() - 1);
+        self.sift_up(self.heap.len() - 1);
+    }
+
+    fn heap_pop(&mut self) -> Option<usize> {
+        let top = *self.heap.first()?;
+        let last = self.heap.pop().unwrap();
+        self.heap_index[top] = None;
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.heap_index[last] = Some(0);
+            self.sift_down(0);
+        }
+        Some(top)
+    }
+
+    fn heap_swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.heap_index[self.heap[a]] = Some(a);
+        self.heap_index[self.heap[b]] = Some(b);
+    }
+
+    fn sift_up(&mut self, mut position: usize) {
+        while position > 0 {
+            let parent = (position - 1) / 2;
+            if self.activity[self.heap[parent]] >= self.activity[self.heap[position]] {
+                break;
+            }
+            self.heap_swap(parent, position);
+            position = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut position: usize) {
+        loop {
+            let mut largest = position;
+            for child in [2 * position + 1, 2 * position + 2] {
+                if child < self.heap.len() && self.activity[self.heap[child]] > self.activity[self.heap[largest]] {
+                    largest = child;
+                }
+            }
+            if largest == position {
+                break;
+            }
+            self.heap_swap(position, largest);
+            position = largest;
+        }
+    }
+}
+
+// Assignments of the inputs that make an output 1, up to `limit` of them, and
+// whether that was all of them. Each model found is blocked with a clause
+// over the inputs before looking for the next
+pub fn models(encoding: &Encoding, limit: usize) -> (Vec<Vec<bool>>, bool) {
+    let mut solver = Solver::new(&encoding.cnf);
+    let n = encoding.inputs.len();
+    let mut found = Vec::new();
+    while found.len() < limit {
+        let Some(model) = solver.solve() else {
+            return (found, true);
+        };
+        let inputs = model[..n].to_vec();
+        let blocking: Vec<i32> = inputs.iter().enumerate()
+            .map(|(i, &value)| if value { -(i as i32 + 1) } else { i as i32 + 1 })
+            .collect();
+        found.push(inputs);
+        if blocking.is_empty() {
+            return (found, true);
+        }
+        solver.add_clause(&blocking);
+    }
+    let complete = solver.solve().is_none();
+    (found, complete)
+}
+
+// Shown instead of the truth table past MAX_TABLE_VARIABLES inputs: whether
+// each output can be 1, and the first few input assignments that make it so
+pub fn sat_report(system: &System) -> String {
+    let mut lines = vec![
+        format!(
+            "Too many inputs for a truth table (at most {}), so these are the assignments the SAT solver finds",
+            MAX_TABLE_VARIABLES
+        ),
+        String::new(),
+    ];
+
+    for (o, output) in system.outputs.iter().enumerate() {
+        let encoding = tseitin(system, o);
+        let (found, complete) = models(&encoding, MODEL_LIMIT);
+        let summary = match (found.len(), complete) {
+            (0, _) => "unsatisfiable, it is always 0".to_string(),
+            (count, true) => format!("satisfiable, {} model{}", count, if count == 1 { "" } else { "s" }),
+            (count, false) => format!("satisfiable, first {} models", count),
+        };
+        lines.push(format!(
+            "{} ({} inputs, {} CNF variables, {} clauses): {}",
+            output.name, encoding.inputs.len(), encoding.cnf.num_vars, encoding.cnf.clauses.len(), summary
+        ));
+        if found.is_empty() {
+            lines.push(String::new());
+            continue;
+        }
+
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::Disabled);
+        table.set_header(encoding.inputs.clone());
+        for model in &found {
+            table.add_row(model.iter().map(|&value| if value { "1" } else { "0" }));
+        }
+        lines.push(table.to_string());
+        lines.push(String::new());
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::random;
+    use crate::eval::{eval, eval_wires};
+    use crate::parser::{parse_system, Dialect};
+    use std::collections::HashMap;
+
+    fn satisfies(clauses: &[Vec<i32>], model: &[bool]) -> bool {
+        clauses.iter().all(|clause| {
+            clause.iter().any(|&literal| model[literal.unsigned_abs() as usize - 1] == (literal > 0))
+        })
+    }
+
+    #[test]
+    fn solver_agrees_with_brute_force() {
+        let mut seed = 0x9e3779b97f4a7c15;
+        for _ in 0..500 {
+            let num_vars = 1 + random(&mut seed, 7);
+            let clauses: Vec<Vec<i32>> = (0..1 + random(&mut seed, 30))
+                .map(|_| {
+                    (0..1 + random(&mut seed, 3))
+                        .map(|_| {
+                            let var = 1 + random(&mut seed, num_vars) as i32;
+                            if random(&mut seed, 2) == 0 { var } else { -var }
+                        })
+                        .collect()
+                })
+                .collect();
+            let cnf = Cnf { num_vars, clauses: clauses.clone() };
+            let brute = (0..1usize << num_vars)
+                .any(|row| satisfies(&clauses, &(0..num_vars).map(|v| row >> v & 1 == 1).collect::<Vec<_>>()));
+            match Solver::new(&cnf).solve() {
+                Some(model) => assert!(satisfies(&clauses, &model), "{:?}", clauses),
+                None => assert!(!brute, "{:?}", clauses),
+            }
+        }
+    }
+
+    #[test]
+    fn contradictory_units_are_unsatisfiable() {
+        let cnf = Cnf { num_vars: 1, clauses: vec![vec![1], vec![-1]] };
+        assert_eq!(Solver::new(&cnf).solve(), None);
+    }
+
+    #[test]
+    fn tseitin_models_match_the_truth_table() {
+        let sources = [
+            "F = A & B | !C",
+            "F = (A ^ B) & (A ^ B ^ C) | !(A -> D)",
+            "let w = A !& B; F = w <-> (C !| A); G = w ^ w",
+            "F = A & !A",
+            "F = 1",
+        ];
+        for source in sources {
+            let system = parse_system(source, Dialect::Standard).unwrap();
+            for (o, output) in system.outputs.iter().enumerate() {
+                let encoding = tseitin(&system, o);
+                let vars = &encoding.inputs;
+                let expected = (0..1usize << vars.len())
+                    .filter(|row| {
+                        let mut map: HashMap<String, bool> = vars.iter().enumerate()
+                            .map(|(i, var)| (var.clone(), row >> i & 1 == 1))
+                            .collect();
+                        eval_wires(&system, &mut map);
+                        eval(&output.expr, &map)
+                    })
+                    .count();
+                let (found, complete) = models(&encoding, 1000);
+                assert!(complete, "{}", source);
+                assert_eq!(found.len(), expected, "{}", source);
+            }
+        }
+    }
+}
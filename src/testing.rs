@@ -0,0 +1,9 @@
+// Helpers shared by the test modules
+
+// xorshift, so every run checks the same cases
+pub fn random(seed: &mut u64, below: usize) -> usize {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 7;
+    *seed ^= *seed << 17;
+    (*seed % below as u64) as usize
+}
@@ -1,8 +1,10 @@
 use itertools::Itertools;
 use std::collections::{HashSet, HashMap};
-use crate::eval::eval;
-use crate::expr::Expr;
+use crate::eval::{eval_output, eval_wires, Logic};
+use crate::expr::{Expr, Output, System};
 use comfy_table::{
+    Cell,
+    Color,
     Table,
     presets::UTF8_FULL,
     modifiers::UTF8_ROUND_CORNERS,
@@ -17,21 +19,79 @@ pub fn variables(expr: &Expr) -> Vec<String> {
     vars
 }
 
+// Inputs shared by all outputs of a system: the declared order of `F(A,B,C)`
+// first, then any others sorted
+pub fn system_variables(system: &System) -> Vec<String> {
+    let mut set = HashSet::new();
+    for definition in &system.definitions {
+        collect_vars(&definition.expr, &mut set);
+    }
+    for output in &system.outputs {
+        collect_vars(&output.expr, &mut set);
+        if let Some(dont_care) = &output.dont_care {
+            collect_vars(dont_care, &mut set);
+        }
+    }
+    let mut rest: Vec<_> = set.into_iter().filter(|v| !system.inputs.contains(v)).collect();
+    rest.sort();
+
+    let mut vars = system.inputs.clone();
+    vars.extend(rest);
+    vars
+}
+
+// The inputs one output actually depends on, in system order
+pub fn output_variables(system: &System, output: &Output) -> Vec<String> {
+    let mut set = HashSet::new();
+    collect_vars(&system.inline(&output.expr), &mut set);
+    if let Some(dont_care) = &output.dont_care {
+        collect_vars(&system.inline(dont_care), &mut set);
+    }
+    system_variables(system).into_iter().filter(|v| set.contains(v)).collect()
+}
+
 fn collect_vars(expr: &Expr, set: &mut HashSet<String>) {
     match expr {
         Expr::Var(name) => { set.insert(name.clone()); }
+        Expr::Const(_) | Expr::Ref(_) => {}
         Expr::Not(inner) => collect_vars(inner, set),
         Expr::And(a, b) | Expr::Or(a, b) 
         | Expr::Xor(a, b) | Expr::Xnor(a, b) 
-        | Expr::Nand(a, b) | Expr::Nor(a, b) => {
+        | Expr::Nand(a, b) | Expr::Nor(a, b)
+        | Expr::Implies(a, b) | Expr::Iff(a, b) => {
             collect_vars(a, set);
             collect_vars(b, set);
         },
     }
 }
 
-pub fn truth_table(expr: &Expr) -> String {
-    let vars = variables(expr);
+// Value of one output on every row over `vars`. Row 0 has every input at 0 and
+// the first variable is the most significant bit, as in minterm numbers
+pub fn output_values(system: &System, output: &Output, vars: &[String]) -> Vec<Logic> {
+    (0..1usize << vars.len())
+        .map(|row| {
+            let mut map = HashMap::new();
+            for (i, var) in vars.iter().enumerate() {
+                map.insert(var.clone(), (row >> (vars.len() - 1 - i)) & 1 == 1);
+            }
+            eval_wires(system, &mut map);
+            eval_output(output, &map)
+        })
+        .collect()
+}
+
+// Past this many inputs the table is too long to be worth building
+pub const MAX_TABLE_VARIABLES: usize = 16;
+
+// One column per input, then optionally one per intermediate wire, then one per output
+pub fn truth_table(system: &System, show_wires: bool) -> String {
+    let vars = system_variables(system);
+    if vars.len() > MAX_TABLE_VARIABLES {
+        return format!(
+            "{} inputs is too many for a truth table (at most {})",
+            vars.len(), MAX_TABLE_VARIABLES
+        );
+    }
 
     let mut table = Table::new();
     table
@@ -40,7 +100,10 @@ pub fn truth_table(expr: &Expr) -> String {
         .set_content_arrangement(ContentArrangement::Dynamic);
 
     let mut header = vars.clone();
-    header.push("OUT".to_string());
+    if show_wires {
+        header.extend(system.definitions.iter().map(|d| d.name.clone()));
+    }
+    header.extend(system.outputs.iter().map(|o| o.name.clone()));
     table.set_header(header);
 
     for combo in (0..vars.len())
@@ -52,15 +115,72 @@ pub fn truth_table(expr: &Expr) -> String {
                 map.insert(var.clone(), *val);
             }
 
-            let result = eval(expr, &map);
-
             let mut row: Vec<String> = combo
                 .iter()
                 .map(|b| if *b { "1".to_string() } else { "0".to_string() })
                 .collect();
 
-            row.push(if result { "1".to_string() } else { "0".to_string() });
+            eval_wires(system, &mut map);
+            if show_wires {
+                for definition in &system.definitions {
+                    row.push(if map[&definition.name] { "1".to_string() } else { "0".to_string() });
+                }
+            }
+
+            for output in &system.outputs {
+                row.push(eval_output(output, &map).to_string());
+            }
             table.add_row(row);
         }
     table.to_string()
 }
+
+// Two systems side by side over `vars`, output by output, with the rows where
+// they disagree marked and, on a terminal, in red. A don't-care on either side
+// agrees with anything
+pub fn comparison_table(first: &System, second: &System, vars: &[String]) -> String {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    let mut header = vars.to_vec();
+    for (a, b) in first.outputs.iter().zip(&second.outputs) {
+        header.push(format!("{} (1)", a.name));
+        header.push(format!("{} (2)", b.name));
+    }
+    header.push(String::new());
+    table.set_header(header);
+
+    for combo in (0..vars.len())
+        .map(|_| [false, true])
+        .multi_cartesian_product()
+        {
+            let mut map = HashMap::new();
+            for (var, val) in vars.iter().zip(combo.iter()) {
+                map.insert(var.clone(), *val);
+            }
+            let mut first_map = map.clone();
+            eval_wires(first, &mut first_map);
+            let mut second_map = map;
+            eval_wires(second, &mut second_map);
+
+            let mut row: Vec<String> = combo
+                .iter()
+                .map(|b| if *b { "1".to_string() } else { "0".to_string() })
+                .collect();
+            let mut differs = false;
+            for (a, b) in first.outputs.iter().zip(&second.outputs) {
+                let (a, b) = (eval_output(a, &first_map), eval_output(b, &second_map));
+                differs |= a != b && a != Logic::DontCare && b != Logic::DontCare;
+                row.push(a.to_string());
+                row.push(b.to_string());
+            }
+            row.push(if differs { "differs".to_string() } else { String::new() });
+
+            let color = if differs { Color::Red } else { Color::Reset };
+            table.add_row(row.into_iter().map(|text| Cell::new(text).fg(color)));
+        }
+    table.to_string()
+}